@@ -1,12 +1,152 @@
-use clap::Parser;
+use std::path::Path;
+
+use clap::{builder::RangedU64ValueParser, Args as ClapArgs, Parser, Subcommand, ValueEnum};
+
+use de_novo_motif_finder::{
+    datastructures::{background::Background, sequence::Sequence},
+    em::EmConfig,
+    fasta_reader::{self, ParseOptions},
+    gibbs::GibbsConfig,
+    motif_finder::{SearchConfig, Seeding, SiteModel},
+    scanner::ScanThreshold,
+    words::{WordConfig, WordControl},
+    Error, Result,
+};
+
+/// Highest Markov order of an estimated background, its tables hold 4^(order + 1) probabilities
+const MAX_BACKGROUND_ORDER: u64 = 8;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackgroundModel {
+    /// Every base equally likely
+    Uniform,
+    /// Base composition of the input sequences
+    Input,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Align the sequences one at a time, keeping the best alignments in a priority queue
+    Beam,
+    /// Gibbs sampling of motifs of the min length, faster on long sequences, only with the oops
+    /// model
+    Gibbs,
+    /// Count every word of the min length with mismatches, reporting the most enriched as motifs
+    /// made of their occurrences, which --refine can improve
+    Words,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WordControlModel {
+    /// The occurrence expected from the background
+    Background,
+    /// The occurrence in the input with the bases of each sequence shuffled
+    Shuffled,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchModel {
+    /// Every sequence has one site
+    Oops,
+    /// Every sequence has zero or one site
+    Zoops,
+    /// Every sequence has any number of sites, found when refining
+    Tcm,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeedingModel {
+    /// Every pair of sequences
+    All,
+    /// Random pairs of sequences, drawn with the seed
+    Sampled,
+    /// The pairs of sequences sharing the most words of the min length
+    Similar,
+}
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Without a subcommand the input is searched for motifs
+    #[command(flatten)]
+    pub search: Option<Args>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Search the input for motifs
+    Search(Args),
+    /// Scan sequences for sites of known motifs, printing them as tab separated values
+    Scan(ScanArgs),
+}
+
+#[derive(ClapArgs, Debug)]
 pub struct Args {
-    /// Path of the input file
+    /// Path of the fasta or fastq input file, gzip and bgzip compressed files are supported, - reads
+    /// stdin
     #[arg(short, long)]
     pub input_file: String,
 
+    /// Control sequences in fasta or fastq, motifs are then ranked by how enriched they are in the
+    /// input compared to these, the word algorithm counts its words in them instead
+    #[arg(long)]
+    pub control_file: Option<String>,
+
+    /// Fraction of the way from the lowest to the highest Pwm score a window must score to count
+    /// as a site of a motif in the input and control sequences
+    #[arg(long, default_value = "0.8")]
+    pub site_threshold: f64,
+
+    /// Treat lowercase bases as soft-masked repeats and leave them out of the search
+    #[arg(long)]
+    pub soft_mask: bool,
+
+    /// Mask fastq bases with a lower Phred quality than this
+    #[arg(long)]
+    pub min_quality: Option<u8>,
+
+    /// Search both strands of every sequence
+    #[arg(long)]
+    pub both_strands: bool,
+
+    /// Base composition the motifs are scored against
+    #[arg(long, value_enum, default_value = "uniform")]
+    pub background: BackgroundModel,
+
+    /// Estimate the background from the sequences in this fasta or fastq file instead
+    #[arg(long, conflicts_with_all = ["background", "background_file"])]
+    pub background_fasta: Option<String>,
+
+    /// Markov order of a background estimated from sequences, 0 uses the base composition only
+    #[arg(
+        long,
+        default_value = "0",
+        value_parser = RangedU64ValueParser::<usize>::new().range(0..=MAX_BACKGROUND_ORDER)
+    )]
+    pub background_order: usize,
+
+    /// Read the background from this MEME background file instead
+    #[arg(long, conflicts_with = "background")]
+    pub background_file: Option<String>,
+
+    /// Write the found motifs to this file, to be used by `scan`. The format follows the extension:
+    /// .meme, .jaspar, .pfm, .transfac, .motif (HOMER), or else count matrices
+    #[arg(long)]
+    pub output_motifs: Option<String>,
+
+    /// Write the found motifs in MEME motif format to this file
+    #[arg(long)]
+    pub output_meme: Option<String>,
+
     /// If you want to create sequence logos
     #[arg(short, long)]
     pub plot_sequence_logos: bool,
@@ -23,7 +163,255 @@ pub struct Args {
     #[arg(long, default_value = "6")]
     pub min_length: usize,
 
-    /// Allowed gap size in motifs
-    #[arg(long, default_value = "1")]
+    /// Allowed gap size in motifs, 0 disables gaps
+    #[arg(long, default_value = "2")]
     pub gap_size: usize,
+
+    /// Log-odds score a column must exceed to be part of a motif
+    #[arg(long, default_value = "0.5", allow_negative_numbers = true)]
+    pub threshold: f64,
+
+    /// How to search for motifs
+    #[arg(long, value_enum, default_value = "beam")]
+    pub algorithm: Algorithm,
+
+    /// Independent runs of the Gibbs sampler
+    #[arg(long, default_value = "20")]
+    pub restarts: usize,
+
+    /// Times the site of every sequence is drawn again in each run of the Gibbs sampler
+    #[arg(long, default_value = "100")]
+    pub gibbs_iterations: usize,
+
+    /// Seed of the Gibbs sampler, of shuffling and of sampled seeding, the same seed gives the
+    /// same motifs
+    #[arg(long, default_value = "0")]
+    pub seed: u64,
+
+    /// Mismatches a window may have to a word and still count as an occurrence of it, at most 3
+    /// for words of up to 13 bases and 2 for longer ones
+    #[arg(long, default_value = "1")]
+    pub mismatches: usize,
+
+    /// Longest gap of wildcards in the middle of the counted words, 0 only counts ungapped words
+    #[arg(long, default_value = "0")]
+    pub word_gap: usize,
+
+    /// What word occurrences are compared to, the control file takes precedence
+    #[arg(long, value_enum, default_value = "background")]
+    pub word_control: WordControlModel,
+
+    /// Which pairs of sequences the beam search starts from
+    #[arg(long, value_enum, default_value = "similar")]
+    pub seeding: SeedingModel,
+
+    /// Amount of sequence pairs the beam search starts from when seeding is sampled or similar
+    #[arg(long, default_value = "10")]
+    pub seed_pairs: usize,
+
+    /// Whether every sequence must have a site of a motif. Records split by masked bases count
+    /// as one sequence per segment
+    #[arg(long, value_enum, default_value = "oops")]
+    pub model: SearchModel,
+
+    /// Natural log units taken off the score of a motif for each sequence left without a site by
+    /// the zoops and tcm models
+    #[arg(long, default_value = "1.0")]
+    pub skip_penalty: f64,
+
+    /// Refine the found motifs by expectation maximization over every window of the input
+    #[arg(long)]
+    pub refine: bool,
+
+    /// Max amount of expectation maximization iterations when refining
+    #[arg(long, default_value = "100")]
+    pub em_iterations: usize,
+
+    /// Amount of best extensions kept each time a sequence is added to a motif
+    #[arg(long, default_value = "3")]
+    pub beam_width: usize,
+
+    /// Size the search queue may grow to before it is shrinked
+    #[arg(long, default_value = "1000000")]
+    pub max_queue_size: usize,
+
+    /// Size the search queue is shrinked to when it grows too large
+    #[arg(long, default_value = "50")]
+    pub shrinked_queue_size: usize,
+}
+
+impl Args {
+    pub fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            soft_mask: self.soft_mask,
+            min_quality: self.min_quality,
+        }
+    }
+
+    /// The background chosen on the command line, `seqs` are the searched sequences.
+    pub fn background(&self, seqs: &[Sequence]) -> Result<Background> {
+        let estimated = self.background_file.is_none()
+            && (self.background_fasta.is_some() || self.background == BackgroundModel::Input);
+        if self.background_order > 0 && !estimated {
+            return Err(Error::InvalidConfig(
+                "--background-order needs --background input or --background-fasta".to_string(),
+            ));
+        }
+
+        if let Some(path) = &self.background_file {
+            return Background::from_meme_file(Path::new(path));
+        }
+
+        if let Some(path) = &self.background_fasta {
+            let records = fasta_reader::read_sequences(Path::new(path), self.parse_options())?;
+            return Ok(Background::markov_from_sequences(
+                records
+                    .iter()
+                    .flat_map(|record| record.segments.iter())
+                    .map(|segment| &segment.sequence),
+                self.background_order,
+            ));
+        }
+
+        Ok(match self.background {
+            BackgroundModel::Uniform => Background::uniform(),
+            BackgroundModel::Input => {
+                Background::markov_from_sequences(seqs, self.background_order)
+            }
+        })
+    }
+
+    /// The expectation maximization configuration used when refining.
+    pub fn em_config(&self) -> EmConfig {
+        EmConfig {
+            model: self.site_model(),
+            max_iterations: self.em_iterations,
+            ..Default::default()
+        }
+    }
+
+    pub fn gibbs_config(&self) -> GibbsConfig {
+        GibbsConfig {
+            restarts: self.restarts,
+            iterations: self.gibbs_iterations,
+            seed: self.seed,
+            ..Default::default()
+        }
+    }
+
+    /// The word search configuration, comparing against `controls` if there are any.
+    pub fn word_config(&self, controls: Option<Vec<Sequence>>) -> WordConfig {
+        let control = match (controls, self.word_control) {
+            (Some(controls), _) => WordControl::Sequences(controls),
+            (None, WordControlModel::Background) => WordControl::Background,
+            (None, WordControlModel::Shuffled) => WordControl::Shuffled { seed: self.seed },
+        };
+
+        WordConfig {
+            max_gap: self.word_gap,
+            mismatches: self.mismatches,
+            control,
+        }
+    }
+
+    fn site_model(&self) -> SiteModel {
+        match self.model {
+            SearchModel::Oops => SiteModel::Oops,
+            SearchModel::Zoops => SiteModel::Zoops,
+            SearchModel::Tcm => SiteModel::Tcm,
+        }
+    }
+
+    fn seeding(&self) -> Seeding {
+        match self.seeding {
+            SeedingModel::All => Seeding::AllPairs,
+            SeedingModel::Sampled => Seeding::Sampled {
+                pairs: self.seed_pairs,
+                seed: self.seed,
+            },
+            SeedingModel::Similar => Seeding::Similar {
+                pairs: self.seed_pairs,
+            },
+        }
+    }
+
+    /// The search configuration, with a uniform background until it is replaced by
+    /// [`Self::background`].
+    pub fn search_config(&self) -> Result<SearchConfig> {
+        let config = SearchConfig {
+            hits: self.hits,
+            min_length: self.min_length,
+            max_length: self.max_length,
+            max_gap: (self.gap_size > 0).then_some(self.gap_size),
+            threshold: self.threshold,
+            beam_width: self.beam_width,
+            max_queue_size: self.max_queue_size,
+            shrinked_queue_size: self.shrinked_queue_size,
+            both_strands: self.both_strands,
+            background: Background::uniform(),
+            site_threshold: self.site_threshold,
+            model: self.site_model(),
+            skip_penalty: self.skip_penalty,
+            seeding: self.seeding(),
+        };
+
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct ScanArgs {
+    /// Path of the fasta or fastq file to scan, gzip and bgzip compressed files are supported, -
+    /// reads stdin
+    #[arg(short, long)]
+    pub input_file: String,
+
+    /// File of the motifs to scan for, as count matrices or in MEME, JASPAR, TRANSFAC or HOMER
+    /// format. The thresholds in HOMER files are ignored, sites pass --p-value or --min-score
+    #[arg(short, long)]
+    pub motif_file: String,
+
+    /// Treat lowercase bases as soft-masked repeats and never report sites in them
+    #[arg(long)]
+    pub soft_mask: bool,
+
+    /// Mask fastq bases with a lower Phred quality than this
+    #[arg(long)]
+    pub min_quality: Option<u8>,
+
+    /// Report sites with at most this p-value, only for backgrounds of order 0
+    #[arg(long, default_value = "1e-4")]
+    pub p_value: f64,
+
+    /// Report sites scoring at least this instead of using a p-value threshold
+    #[arg(long, allow_negative_numbers = true)]
+    pub min_score: Option<f64>,
+
+    /// Read the background from this MEME background file instead of using a uniform one
+    #[arg(long)]
+    pub background_file: Option<String>,
+}
+
+impl ScanArgs {
+    pub fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            soft_mask: self.soft_mask,
+            min_quality: self.min_quality,
+        }
+    }
+
+    pub fn background(&self) -> Result<Background> {
+        match &self.background_file {
+            Some(path) => Background::from_meme_file(Path::new(path)),
+            None => Ok(Background::uniform()),
+        }
+    }
+
+    pub fn threshold(&self) -> ScanThreshold {
+        match self.min_score {
+            Some(min_score) => ScanThreshold::Score(min_score),
+            None => ScanThreshold::PValue(self.p_value),
+        }
+    }
 }
@@ -0,0 +1,293 @@
+use std::{collections::HashMap, io::BufRead, path::Path};
+
+use super::{base::Base, sequence::Sequence};
+use crate::{fasta_reader::open_input, Error, Result};
+
+/// The model of the sequence around the motifs: a base composition, optionally with a k-th order
+/// Markov chain giving the probability of each base from the k bases before it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Background {
+    /// Probability of each base, in the order A, C, G, T
+    pub frequencies: [f64; 4],
+    /// Probability of each base given the bases before it. `transitions[k - 1]` holds the order k
+    /// probabilities, indexed by the k preceding bases read as a base 4 number.
+    transitions: Vec<Vec<[f64; 4]>>,
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Self::uniform()
+    }
+}
+
+impl Background {
+    /// Every base equally likely.
+    pub fn uniform() -> Self {
+        Self {
+            frequencies: [0.25; 4],
+            transitions: Vec::new(),
+        }
+    }
+
+    /// Creates a background from base frequencies, which are normalized to sum to 1.
+    pub fn from_frequencies(frequencies: [f64; 4]) -> Result<Self> {
+        Ok(Self {
+            frequencies: normalize(frequencies)?,
+            transitions: Vec::new(),
+        })
+    }
+
+    /// Estimates the base composition of `seqs`, with a pseudocount of one for each base so no
+    /// base gets a zero probability.
+    pub fn from_sequences<'a>(seqs: impl IntoIterator<Item = &'a Sequence>) -> Self {
+        Self::markov_from_sequences(seqs, 0)
+    }
+
+    /// Trains a Markov chain of the given order on `seqs`, with a pseudocount of one for each base
+    /// in every context.
+    pub fn markov_from_sequences<'a>(
+        seqs: impl IntoIterator<Item = &'a Sequence>,
+        order: usize,
+    ) -> Self {
+        // counts[k] holds the counts of each base after every context of k bases
+        let mut counts = (0..=order)
+            .map(|k| vec![[1.0; 4]; 4usize.pow(k as u32)])
+            .collect::<Vec<_>>();
+
+        for seq in seqs {
+            for i in 0..seq.len() {
+                for (k, counts) in counts.iter_mut().enumerate().take(i.min(order) + 1) {
+                    let context = context_index(&seq.bases[i - k..i]);
+                    counts[context][seq[i].to_index()] += 1.0;
+                }
+            }
+        }
+
+        let mut tables = counts.into_iter().map(|counts| {
+            counts
+                .into_iter()
+                .map(|counts| normalize(counts).expect("counts are positive"))
+                .collect::<Vec<_>>()
+        });
+
+        Self {
+            frequencies: tables.next().expect("order 0 is always counted")[0],
+            transitions: tables.collect(),
+        }
+    }
+
+    /// Reads a MEME style background file, with lines of a word and its frequency and `#`
+    /// comments. Words of one base give the base composition, longer words the frequencies of
+    /// higher orders.
+    pub fn from_meme_file(path: &Path) -> Result<Self> {
+        Self::from_meme(open_input(path)?)
+    }
+
+    /// See [`Self::from_meme_file`].
+    pub fn from_meme(reader: impl BufRead) -> Result<Self> {
+        let mut words = HashMap::new();
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let parse_error = |message: String| Error::Parse {
+                line: i + 1,
+                message,
+            };
+
+            let (word, frequency) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| parse_error(format!("expected a word and a frequency: {}", line)))?;
+            let frequency = frequency
+                .trim()
+                .parse::<f64>()
+                .map_err(|e| parse_error(format!("invalid frequency {:?}: {}", frequency, e)))?;
+            let word = word
+                .chars()
+                .map(|c| Base::try_from(c.to_ascii_uppercase()))
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(parse_error)?;
+
+            words.insert(word, frequency);
+        }
+
+        let order = words.keys().map(|word| word.len()).max().unwrap_or(0);
+        if order == 0 {
+            return Err(Error::EmptyInput(
+                "background has no frequencies".to_string(),
+            ));
+        }
+
+        // The frequency of each base after every context, for contexts of 0 to order - 1 bases
+        let mut tables = (0..order).map(|k| {
+            (0..4usize.pow(k as u32))
+                .map(|context| {
+                    let mut frequencies = [0.0; 4];
+                    for (b, frequency) in frequencies.iter_mut().enumerate() {
+                        let word = context_bases(context, k)
+                            .chain(std::iter::once(Base::try_from(b).unwrap()))
+                            .collect::<Vec<_>>();
+                        *frequency = *words.get(&word).ok_or_else(|| {
+                            Error::EmptyInput(format!(
+                                "background has no frequency for {}",
+                                word.iter().map(|&b| char::from(b)).collect::<String>()
+                            ))
+                        })?;
+                    }
+
+                    normalize(frequencies)
+                })
+                .collect::<Result<Vec<_>>>()
+        });
+
+        Ok(Self {
+            frequencies: tables.next().expect("order is at least 1")?[0],
+            transitions: tables.collect::<Result<_>>()?,
+        })
+    }
+
+    /// The Markov order, 0 when only the base composition is used.
+    pub fn order(&self) -> usize {
+        self.transitions.len()
+    }
+
+    pub fn probability(&self, base: Base) -> f64 {
+        self.frequencies[base.to_index()]
+    }
+
+    /// Probability of `base` following `context`, of which only the last [`Self::order`] bases
+    /// are used.
+    pub fn conditional_probability(&self, context: &[Base], base: Base) -> f64 {
+        let k = context.len().min(self.order());
+        if k == 0 {
+            return self.probability(base);
+        }
+
+        let context = context_index(&context[context.len() - k..]);
+        self.transitions[k - 1][context][base.to_index()]
+    }
+
+    /// Log2 likelihood of `len` bases of `seq` from `start`. The bases before `start` are used as
+    /// context for the first positions of the window.
+    pub fn window_log_likelihood(&self, seq: &Sequence, start: usize, len: usize) -> f64 {
+        (start..start + len)
+            .map(|i| {
+                let context = &seq.bases[i.saturating_sub(self.order())..i];
+                self.conditional_probability(context, seq[i]).log2()
+            })
+            .sum()
+    }
+
+    /// Log2 likelihood of the whole of `seq`.
+    pub fn log_likelihood(&self, seq: &Sequence) -> f64 {
+        self.window_log_likelihood(seq, 0, seq.len())
+    }
+}
+
+fn normalize(frequencies: [f64; 4]) -> Result<[f64; 4]> {
+    if frequencies.iter().any(|&f| !f.is_finite() || f <= 0.0) {
+        return Err(Error::InvalidConfig(format!(
+            "background frequencies must be positive, got {:?}",
+            frequencies
+        )));
+    }
+
+    let total = frequencies.iter().sum::<f64>();
+    Ok(frequencies.map(|f| f / total))
+}
+
+fn context_index(context: &[Base]) -> usize {
+    context
+        .iter()
+        .fold(0, |index, base| index * 4 + base.to_index())
+}
+
+/// The `k` bases of the context with the given index.
+fn context_bases(index: usize, k: usize) -> impl Iterator<Item = Base> {
+    (0..k)
+        .rev()
+        .map(move |i| Base::try_from((index / 4usize.pow(i as u32)) % 4).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_sequences() {
+        let background = Background::from_sequences(&[Sequence::from("GGGCCA")]);
+
+        assert_eq!(background.frequencies, [0.2, 0.3, 0.4, 0.1]);
+        assert_eq!(background.order(), 0);
+    }
+
+    #[test]
+    fn test_from_meme() {
+        let input = "# order 0\nA 0.3\nC 0.2\nG 0.2\nT 0.3\n";
+
+        let background = Background::from_meme(input.as_bytes()).unwrap();
+
+        for (f, expected) in background.frequencies.iter().zip([0.3, 0.2, 0.2, 0.3]) {
+            assert!((f - expected).abs() < 1e-9, "{:?}", background);
+        }
+    }
+
+    #[test]
+    fn test_from_meme_missing_base() {
+        let input = "A 0.3\nC 0.2\nG 0.5\n";
+
+        assert!(Background::from_meme(input.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_from_meme_order_1() {
+        let mut input = String::from("A 0.25\nC 0.25\nG 0.25\nT 0.25\n");
+        for first in ['A', 'C', 'G', 'T'] {
+            for second in ['A', 'C', 'G', 'T'] {
+                // CG is depleted
+                let frequency = if (first, second) == ('C', 'G') {
+                    0.01
+                } else {
+                    0.0625
+                };
+                input.push_str(&format!("{}{} {}\n", first, second, frequency));
+            }
+        }
+
+        let background = Background::from_meme(input.as_bytes()).unwrap();
+
+        assert_eq!(background.order(), 1);
+        assert!(
+            background.conditional_probability(&[Base::C], Base::G)
+                < background.conditional_probability(&[Base::C], Base::C)
+        );
+        assert_eq!(
+            background.conditional_probability(&[Base::A], Base::G),
+            0.25
+        );
+    }
+
+    #[test]
+    fn test_markov_log_likelihood() {
+        let background = Background::markov_from_sequences(&[Sequence::from("ACACACACACAC")], 1);
+
+        assert_eq!(background.order(), 1);
+        assert!(
+            background.log_likelihood(&Sequence::from("ACAC"))
+                > background.log_likelihood(&Sequence::from("AACC"))
+        );
+
+        // The base before the window is used as context
+        let seq = Sequence::from("AC");
+        assert_eq!(
+            background.window_log_likelihood(&seq, 1, 1),
+            background
+                .conditional_probability(&[Base::A], Base::C)
+                .log2()
+        );
+    }
+}
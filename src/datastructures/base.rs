@@ -1,4 +1,5 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A DNA nucleotide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Base {
     A,
     C,
@@ -45,6 +46,17 @@ impl From<Base> for char {
 }
 
 impl Base {
+    /// The base it pairs with on the other strand.
+    pub fn complement(self) -> Base {
+        match self {
+            Base::A => Base::T,
+            Base::C => Base::G,
+            Base::G => Base::C,
+            Base::T => Base::A,
+        }
+    }
+
+    /// Column of the base in a [`BaseMatrix`](super::base_matrix::BaseMatrix) row.
     pub fn to_index(self) -> usize {
         match self {
             Base::A => 0,
@@ -6,6 +6,7 @@ use itertools::Itertools;
 
 use super::{base::Base, sequence::Sequence};
 
+/// A matrix with one row per motif position and one column per base, in the order A, C, G, T.
 #[derive(Debug, Clone, PartialEq)]
 pub struct BaseMatrix<T> {
     pub matrix: Vec<[T; 4]>,
@@ -18,6 +19,7 @@ impl<T> BaseMatrix<T>
 where
     T: Clone + PartialOrd,
 {
+    /// Returns the rows in `range` as a new matrix.
     pub fn slice(
         &self,
         range: impl RangeBounds<usize> + SliceIndex<[[T; 4]], Output = [[T; 4]]> + Clone,
@@ -30,10 +32,37 @@ where
         }
     }
 
+    /// Number of positions in the matrix.
     pub fn len(&self) -> usize {
         self.matrix.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.matrix.is_empty()
+    }
+
+    /// The matrix of the opposite strand, with the positions reversed and each base swapped for
+    /// its complement.
+    pub fn reverse_complement(&self) -> Self {
+        let matrix = self
+            .matrix
+            .iter()
+            .rev()
+            .map(|row| {
+                // With the columns ordered A, C, G, T, reversing a row complements it
+                let mut row = row.clone();
+                row.reverse();
+                row
+            })
+            .collect();
+
+        Self {
+            matrix,
+            sample_size: self.sample_size,
+        }
+    }
+
+    /// The most frequent base at each position.
     pub fn get_consensus_string(&self) -> String {
         self.matrix
             .iter()
@@ -54,6 +83,7 @@ where
             .collect()
     }
 
+    /// Same as [`Self::get_consensus_string`], but as a [`Sequence`].
     pub fn get_consensus_sequence(&self) -> Sequence {
         self.matrix
             .iter()
@@ -1,5 +1,9 @@
+//! Bases, sequences and the matrices built from aligned sequences.
+
+pub mod background;
 pub mod base;
 pub mod base_matrix;
 pub mod pfm;
 pub mod pwm;
+pub mod score_distribution;
 pub mod sequence;
@@ -2,11 +2,14 @@
 
 use itertools::Itertools;
 
-use super::{base_matrix::BaseMatrix, sequence::Sequence};
+use super::{background::Background, base_matrix::BaseMatrix, pwm::Pwm, sequence::Sequence};
+use crate::stats;
 
+/// Position frequency matrix, counting how many times each base is seen at each position.
 pub type Pfm = BaseMatrix<usize>;
 
 impl Pfm {
+    /// Creates a pfm from equally long sequences.
     pub fn from_sequences(seqs: &[Sequence]) -> Self {
         assert!(seqs.iter().map(|a| a.len()).all_equal());
 
@@ -24,6 +27,7 @@ impl Pfm {
         }
     }
 
+    /// Counts the bases of `seq`, which must be as long as the pfm.
     pub fn add_sequence(&mut self, seq: &Sequence) {
         assert_eq!(self.len(), seq.len());
 
@@ -34,6 +38,7 @@ impl Pfm {
         }
     }
 
+    /// Adds `pseudocount` to every count.
     pub fn additive_smoothing(&mut self, pseudocount: usize) {
         for row in self.matrix.iter_mut() {
             for count in row.iter_mut() {
@@ -44,50 +49,108 @@ impl Pfm {
         self.sample_size += pseudocount * 4;
     }
 
-    /// Assums ACTG is uniform. Must give pfm
-    pub fn kullback_leibler_divergence(&self) -> f64 {
-        let uniform = 1.0 / 4.0; // Assuming a uniform distribution of ACTG
-        let mut kl_divergence = 0.0;
-        let pseudo_count = 0.0001; // To avoid log(0)
-
-        for row in self.matrix.iter() {
-            for &p in row.iter() {
-                let p = p as f64 + pseudo_count;
+    /// Relative entropy in bits of the pfm's base frequencies to `background`, summed over the
+    /// columns.
+    pub fn kullback_leibler_divergence(&self, background: &Background) -> f64 {
+        Pwm::pfm_into_ppm(self.clone())
+            .column_relative_entropy(background)
+            .iter()
+            .sum()
+    }
 
-                kl_divergence += p * (p / uniform).ln();
-            }
-        }
+    /// Information content in bits of each column against `background`, corrected for the
+    /// sample size, see [`Pwm::column_information_content`].
+    pub fn column_information_content(&self, background: &Background) -> Vec<f64> {
+        Pwm::pfm_into_ppm(self.clone()).column_information_content(background)
+    }
 
-        kl_divergence
+    /// Information content in bits of the whole pfm, the sum of its columns.
+    pub fn information_content(&self, background: &Background) -> f64 {
+        self.column_information_content(background).iter().sum()
     }
 
-    //https://www.maths.usyd.edu.au/u/uri/my_papers/2006_Evalue_finders_RecombRG_draft.pdf
-    pub fn get_custom_score(&self) -> f64 {
-        let smoothing = 0.001;
+    /// Log likelihood ratio in nats of the counted bases under the pfm's own frequencies versus
+    /// under `background`.
+    pub fn log_likelihood_ratio(&self, background: &Background) -> f64 {
         self.matrix
             .iter()
-            .map(|row| {
-                row.iter()
-                    .map(|&val| {
-                        (val as f64 + smoothing)
-                            * (((val as f64 + smoothing) / self.sample_size as f64) / 0.25).log2()
-                    })
-                    .sum::<f64>()
+            .flat_map(|row| row.iter().zip(background.frequencies))
+            .filter(|(&count, _)| count > 0)
+            .map(|(&count, q)| {
+                let count = count as f64;
+                count * (count / (self.sample_size as f64 * q)).ln()
             })
             .sum()
     }
+
+    /// Natural log of the p-value of `log_likelihood_ratio`. Twice the ratio is approximately
+    /// chi-square distributed with three degrees of freedom for each column.
+    //https://www.maths.usyd.edu.au/u/uri/my_papers/2006_Evalue_finders_RecombRG_draft.pdf
+    pub fn log_p_value(&self, log_likelihood_ratio: f64) -> f64 {
+        stats::ln_chi_square_upper_tail(2.0 * log_likelihood_ratio, 3 * self.len())
+    }
+
+    /// Significance of the pfm against `background`, minus the natural log of its p-value, so it
+    /// can be compared between motifs of different widths and sample sizes.
+    pub fn get_custom_score(&self, background: &Background) -> f64 {
+        -self.log_p_value(self.log_likelihood_ratio(background))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_reverse_complement() {
+        let seqs = [Sequence::from("AACGTG"), Sequence::from("TACGGG")];
+        let reverse = seqs
+            .iter()
+            .map(|seq| seq.reverse_complement())
+            .collect_vec();
+
+        assert_eq!(
+            Pfm::from_sequences(&seqs).reverse_complement(),
+            Pfm::from_sequences(&reverse)
+        );
+    }
+
+    #[test]
+    fn test_kullback_leibler_divergence() {
+        let pfm = Pfm::from_sequences(&["AC".into(), "AG".into()]);
+
+        // Two bits for the conserved column and one for the column split between two bases
+        assert_eq!(pfm.kullback_leibler_divergence(&Background::uniform()), 3.0);
+        // Independent of the sample size
+        let mut doubled = pfm.clone();
+        doubled.add_sequence(&"AC".into());
+        doubled.add_sequence(&"AG".into());
+        assert_eq!(
+            doubled.kullback_leibler_divergence(&Background::uniform()),
+            3.0
+        );
+    }
+
+    #[test]
+    fn test_information_content_small_sample_correction() {
+        let few = Pfm::from_sequences(&vec![Sequence::from("ACGT"); 2]);
+        let many = Pfm::from_sequences(&vec![Sequence::from("ACGT"); 100]);
+
+        let few = few.information_content(&Background::uniform());
+        let many = many.information_content(&Background::uniform());
+        assert!(few < many, "{} < {}", few, many);
+        assert!(many < 8.0 && many > 7.9, "{}", many);
+    }
+
     #[test]
     fn test_get_custom_score_1() {
         let pfm_best = Pfm::from_sequences(&["ACGT".into(), "ACGT".into()]);
         let pfm_not_best = Pfm::from_sequences(&["AACGT".into(), "CACGT".into()]);
 
-        assert!(pfm_best.get_custom_score() > pfm_not_best.get_custom_score());
+        assert!(
+            pfm_best.get_custom_score(&Background::uniform())
+                > pfm_not_best.get_custom_score(&Background::uniform())
+        );
     }
 
     #[test]
@@ -96,10 +159,11 @@ mod tests {
         let pfm_not_best = Pfm::from_sequences(&["CCGT".into(), "ACGT".into()]);
 
         assert!(
-            pfm_best.get_custom_score() > pfm_not_best.get_custom_score(),
+            pfm_best.get_custom_score(&Background::uniform())
+                > pfm_not_best.get_custom_score(&Background::uniform()),
             "{:?} > {:?}",
-            pfm_best.get_custom_score(),
-            pfm_not_best.get_custom_score()
+            pfm_best.get_custom_score(&Background::uniform()),
+            pfm_not_best.get_custom_score(&Background::uniform())
         );
     }
 }
@@ -1,5 +1,6 @@
-use super::{base_matrix::BaseMatrix, pfm::Pfm};
+use super::{background::Background, base_matrix::BaseMatrix, pfm::Pfm, sequence::Sequence};
 
+/// Position weight matrix, with one score per base at each position.
 pub type Pwm = BaseMatrix<f64>;
 
 impl Pwm {
@@ -16,19 +17,16 @@ impl Pwm {
             sample_size: pfm.sample_size,
         }
     }
-}
 
-impl From<Pfm> for Pwm {
-    /// Converts a Pfm to a Pwm using log-odds scoring
-    fn from(pfm: Pfm) -> Self {
+    /// Converts a Pfm to a Pwm of log2-odds scores against the base composition of `background`
+    pub fn log_odds(pfm: &Pfm, background: &Background) -> Pwm {
         let mut matrix = vec![[0.; 4]; pfm.matrix.len()];
-        let background = 0.25;
 
         for (i, row) in pfm.matrix.iter().enumerate() {
             for (j, count) in row.iter().enumerate() {
                 let p = *count as f64 / pfm.sample_size as f64;
                 let adjusted_p = if p == 0.0 { 1e-6 } else { p };
-                matrix[i][j] = (adjusted_p / background).log2();
+                matrix[i][j] = (adjusted_p / background.frequencies[j]).log2();
             }
         }
 
@@ -37,9 +35,158 @@ impl From<Pfm> for Pwm {
             sample_size: pfm.sample_size,
         }
     }
+
+    /// Relative entropy in bits of each column to `background`, for a Pwm of base probabilities
+    /// made by [`Self::pfm_into_ppm`].
+    pub fn column_relative_entropy(&self, background: &Background) -> Vec<f64> {
+        self.matrix
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(background.frequencies)
+                    .filter(|(&p, _)| p > 0.0)
+                    .map(|(&p, q)| p * (p / q).log2())
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// Information content in bits of each column against `background`, for a Pwm of base
+    /// probabilities made by [`Self::pfm_into_ppm`]. Few sequences make columns look more
+    /// conserved than they are, so the expected bias of `3 / (2 ln 2 n)` bits for a sample of `n`
+    /// sequences is subtracted (Schneider et al. 1986), never going below zero.
+    pub fn column_information_content(&self, background: &Background) -> Vec<f64> {
+        let correction = if self.sample_size == 0 {
+            0.0
+        } else {
+            3.0 / (2.0 * std::f64::consts::LN_2 * self.sample_size as f64)
+        };
+
+        self.column_relative_entropy(background)
+            .into_iter()
+            .map(|bits| (bits - correction).max(0.0))
+            .collect()
+    }
+
+    /// Information content in bits of the whole matrix, see
+    /// [`Self::column_information_content`].
+    pub fn information_content(&self, background: &Background) -> f64 {
+        self.column_information_content(background).iter().sum()
+    }
+
+    /// Highest score any window can get.
+    pub fn max_score(&self) -> f64 {
+        self.matrix
+            .iter()
+            .map(|row| row.iter().copied().fold(f64::NEG_INFINITY, f64::max))
+            .sum()
+    }
+
+    /// Lowest score any window can get.
+    pub fn min_score(&self) -> f64 {
+        self.matrix
+            .iter()
+            .map(|row| row.iter().copied().fold(f64::INFINITY, f64::min))
+            .sum()
+    }
+
+    /// Log2-odds score of the window of `seq` starting at `start`, for a Pwm made by
+    /// [`Self::log_odds`] with the same `background`. With a Markov background the base
+    /// composition is swapped for the likelihood of the window in its context.
+    pub fn score_window(&self, seq: &Sequence, start: usize, background: &Background) -> f64 {
+        let score = self
+            .matrix
+            .iter()
+            .enumerate()
+            .map(|(i, row)| row[seq[start + i].to_index()])
+            .sum::<f64>();
+
+        if background.order() == 0 {
+            return score;
+        }
+
+        let composition = (start..start + self.len())
+            .map(|i| background.probability(seq[i]).log2())
+            .sum::<f64>();
+        score + composition - background.window_log_likelihood(seq, start, self.len())
+    }
+}
+
+impl From<Pfm> for Pwm {
+    /// Converts a Pfm to a Pwm using log-odds scoring against a uniform background
+    fn from(pfm: Pfm) -> Self {
+        Pwm::log_odds(&pfm, &Background::uniform())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::datastructures::sequence::Sequence;
+
+    #[test]
+    fn test_log_odds_background() {
+        let pfm = Pfm::from_sequences(&["AC".into(), "AG".into()]);
+        let background = Background::from_frequencies([0.5, 0.25, 0.125, 0.125]).unwrap();
+
+        let pwm = Pwm::log_odds(&pfm, &background);
+
+        assert_eq!(pwm.matrix[0][0], 1.0);
+        assert_eq!(pwm.matrix[1][1], 1.0);
+        assert_eq!(pwm.matrix[1][2], 2.0);
+    }
+
+    #[test]
+    fn test_score_window_markov() {
+        let pfm = Pfm::from_sequences(&["CG".into(), "CG".into()]);
+        let seq = Sequence::from("ACGT");
+        // Same base composition, but only the first often has a G after a C
+        let common = Background::markov_from_sequences(&[Sequence::from("CGCGCGCG")], 1);
+        let rare = Background::markov_from_sequences(&[Sequence::from("CCCCGGGG")], 1);
+        assert_eq!(common.frequencies, rare.frequencies);
+
+        let pwm = Pwm::log_odds(&pfm, &common);
+
+        assert!(pwm.score_window(&seq, 1, &rare) > pwm.score_window(&seq, 1, &common));
+        assert_eq!(
+            pwm.score_window(&seq, 1, &Background::uniform()),
+            pwm.matrix[0][1] + pwm.matrix[1][2]
+        );
+    }
+
+    #[test]
+    fn test_column_information_content() {
+        let ppm = Pwm {
+            matrix: vec![[1.0, 0.0, 0.0, 0.0], [0.25; 4], [0.5, 0.5, 0.0, 0.0]],
+            sample_size: 0,
+        };
+
+        assert_eq!(
+            ppm.column_information_content(&Background::uniform()),
+            vec![2.0, 0.0, 1.0]
+        );
+
+        // A conserved A is less surprising when A is common
+        let background = Background::from_frequencies([0.5, 0.25, 0.125, 0.125]).unwrap();
+        assert_eq!(ppm.column_relative_entropy(&background)[0], 1.0);
+
+        // Two sequences give a correction of 3 / (4 ln 2) bits
+        let ppm = Pwm {
+            sample_size: 2,
+            ..ppm
+        };
+        let corrected = ppm.column_information_content(&Background::uniform());
+        assert!((corrected[0] - (2.0 - 3.0 / (4.0 * std::f64::consts::LN_2))).abs() < 1e-12);
+        assert_eq!(corrected[1], 0.0);
+    }
+
+    #[test]
+    fn test_from_pfm_uses_uniform_background() {
+        let pfm = Pfm::from_sequences(&[Sequence::from("ACGT"), Sequence::from("AGGT")]);
+
+        assert_eq!(
+            Pwm::from(pfm.clone()),
+            Pwm::log_odds(&pfm, &Background::uniform())
+        );
+    }
 }
@@ -0,0 +1,195 @@
+use super::{background::Background, pwm::Pwm, sequence::Sequence};
+
+/// Resolution in bits the Pwm scores are rounded to by [`ScoreDistribution::new`]
+pub const DEFAULT_RESOLUTION: f64 = 0.01;
+
+/// Exact distribution of the scores a Pwm gives windows drawn from the base composition of a
+/// background, computed by dynamic programming over the columns of the Pwm with its scores
+/// rounded to a fixed resolution.
+///
+/// ```
+/// use de_novo_motif_finder::datastructures::{
+///     background::Background, pfm::Pfm, pwm::Pwm, score_distribution::ScoreDistribution,
+/// };
+///
+/// let pfm = Pfm::from_sequences(&["GATTACA".into()]);
+/// let pwm = Pwm::log_odds(&pfm, &Background::uniform());
+/// let distribution = ScoreDistribution::new(&pwm, &Background::uniform());
+///
+/// // Only the one matching window of the 4^7 gets the highest score
+/// assert!((distribution.p_value(pwm.max_score()) - 0.25f64.powi(7)).abs() < 1e-12);
+/// assert_eq!(distribution.score(1e-4), pwm.max_score());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScoreDistribution {
+    resolution: f64,
+    /// Rounded score of the lowest entry of `tail`, in units of the resolution
+    offset: i64,
+    /// Probability of a window scoring at least `offset + i` for each `i`
+    tail: Vec<f64>,
+}
+
+impl ScoreDistribution {
+    pub fn new(pwm: &Pwm, background: &Background) -> Self {
+        Self::with_resolution(pwm, background, DEFAULT_RESOLUTION)
+    }
+
+    /// Computes the distribution with the Pwm scores rounded to multiples of `resolution`. A finer
+    /// resolution is more exact, but takes more time and memory.
+    pub fn with_resolution(pwm: &Pwm, background: &Background, resolution: f64) -> Self {
+        assert!(resolution > 0.0, "resolution must be positive");
+
+        let columns = pwm
+            .matrix
+            .iter()
+            .map(|row| row.map(|score| (score / resolution).round() as i64))
+            .collect::<Vec<_>>();
+        let offset = columns
+            .iter()
+            .map(|row| row.iter().min().unwrap())
+            .sum::<i64>();
+
+        // probabilities[i] is the probability of the columns so far summing to their minimum + i
+        let mut probabilities = vec![1.0];
+        for row in columns.iter() {
+            let min = *row.iter().min().unwrap();
+            let max = *row.iter().max().unwrap();
+            let mut next = vec![0.0; probabilities.len() + (max - min) as usize];
+
+            for (i, &p) in probabilities.iter().enumerate().filter(|(_, &p)| p > 0.0) {
+                for (score, q) in row.iter().zip(background.frequencies) {
+                    next[i + (score - min) as usize] += p * q;
+                }
+            }
+
+            probabilities = next;
+        }
+
+        let mut tail = probabilities;
+        for i in (0..tail.len() - 1).rev() {
+            tail[i] += tail[i + 1];
+        }
+
+        Self {
+            resolution,
+            offset,
+            tail,
+        }
+    }
+
+    /// Probability of a window from the background scoring at least `score`. The Pwm scores are
+    /// rounded before they are summed, so this is exact up to half the resolution for each column,
+    /// see [`Self::window_p_value`] for the exact p-value of a window.
+    pub fn p_value(&self, score: f64) -> f64 {
+        self.tail_from((score / self.resolution).round() as i64)
+    }
+
+    /// Exact p-value of the window of `seq` starting at `start`, scored by `pwm` or its reverse
+    /// complement, which have the same distribution.
+    pub fn window_p_value(&self, pwm: &Pwm, seq: &Sequence, start: usize) -> f64 {
+        let score = pwm
+            .matrix
+            .iter()
+            .enumerate()
+            .map(|(i, row)| (row[seq[start + i].to_index()] / self.resolution).round() as i64)
+            .sum();
+
+        self.tail_from(score)
+    }
+
+    /// The lowest score a window can get with at most the given p-value, or infinity if no score
+    /// is that rare.
+    pub fn score(&self, p_value: f64) -> f64 {
+        // tail decreases, so the scores with a low enough p-value are at its end. Only the scores
+        // with a probability of their own can be the score of a window.
+        let index = (self.tail.partition_point(|&p| p > p_value)..self.tail.len())
+            .find(|&i| self.tail[i] > self.tail.get(i + 1).copied().unwrap_or(0.0));
+
+        match index {
+            Some(index) => (index as i64 + self.offset) as f64 * self.resolution,
+            None => f64::INFINITY,
+        }
+    }
+
+    /// Probability of the rounded score being at least `score` units of the resolution.
+    fn tail_from(&self, score: i64) -> f64 {
+        let index = score - self.offset;
+
+        if index <= 0 {
+            1.0
+        } else {
+            self.tail.get(index as usize).map_or(0.0, |&p| p.min(1.0))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datastructures::{base::Base, pfm::Pfm};
+
+    #[test]
+    fn test_p_value_matches_enumeration() {
+        let pfm = Pfm::from_sequences(&[
+            Sequence::from("ACG"),
+            Sequence::from("ACT"),
+            Sequence::from("GCT"),
+        ]);
+        let background = Background::from_frequencies([0.3, 0.2, 0.2, 0.3]).unwrap();
+        let pwm = Pwm::log_odds(&pfm, &background);
+        let distribution = ScoreDistribution::with_resolution(&pwm, &background, 0.001);
+
+        // Every window of three bases with its probability and score
+        let windows = (0..64)
+            .map(|i| {
+                let seq = [i / 16, i / 4 % 4, i % 4]
+                    .into_iter()
+                    .map(|b| Base::try_from(b).unwrap())
+                    .collect::<Sequence>();
+                let probability = seq
+                    .bases
+                    .iter()
+                    .map(|&b| background.probability(b))
+                    .product::<f64>();
+                let score = pwm.score_window(&seq, 0, &background);
+                (seq, probability, score)
+            })
+            .collect::<Vec<_>>();
+
+        for (seq, _, threshold) in windows.iter() {
+            let expected = windows
+                .iter()
+                .filter(|(_, _, score)| *score >= threshold - 1e-9)
+                .map(|(_, p, _)| p)
+                .sum::<f64>();
+            let p_value = distribution.window_p_value(&pwm, seq, 0);
+
+            assert!(
+                (p_value - expected).abs() < 1e-9,
+                "{} {}",
+                p_value,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_score_for_p_value() {
+        let pfm = Pfm::from_sequences(&[Sequence::from("GATTACA")]);
+        let pwm = Pwm::log_odds(&pfm, &Background::uniform());
+        let distribution = ScoreDistribution::new(&pwm, &Background::uniform());
+
+        for p_value in [1e-4, 1e-3, 0.01, 0.5] {
+            let score = distribution.score(p_value);
+            assert!(distribution.p_value(score) <= p_value);
+        }
+        // One mismatch is 6 * 2 bits and one log2(1e-6 / 0.25), and has a p-value of
+        // 0.25^7 + 7 * 0.75 * 0.25^6, two mismatches have a p-value above 0.01
+        assert!((distribution.score(0.01) - -5.93).abs() < 1e-9);
+        assert!(distribution.p_value(-5.93) < 0.01);
+        assert!(distribution.p_value(-25.87) > 0.01);
+        assert_eq!(distribution.p_value(pwm.min_score()), 1.0);
+        assert_eq!(distribution.p_value(pwm.max_score() + 1.0), 0.0);
+        assert_eq!(distribution.score(0.0), f64::INFINITY);
+    }
+}
@@ -1,12 +1,29 @@
 use std::{
-    fmt::Debug,
+    fmt::{self, Debug},
     ops::{Index, RangeBounds},
     slice::SliceIndex,
 };
 
 use super::base::Base;
 
-#[derive(Clone, PartialEq)]
+/// Which strand of a sequence something is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strand {
+    Forward,
+    Reverse,
+}
+
+impl fmt::Display for Strand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Strand::Forward => write!(f, "+"),
+            Strand::Reverse => write!(f, "-"),
+        }
+    }
+}
+
+/// A DNA sequence.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Sequence {
     pub bases: Vec<Base>,
 }
@@ -16,6 +33,20 @@ impl Sequence {
         self.bases.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.bases.is_empty()
+    }
+
+    /// The sequence of the opposite strand, read in its own 5' to 3' direction.
+    pub fn reverse_complement(&self) -> Self {
+        self.bases
+            .iter()
+            .rev()
+            .map(|base| base.complement())
+            .collect()
+    }
+
+    /// Returns the bases in `range` as a new sequence.
     pub fn slice(
         &self,
         range: impl RangeBounds<usize> + SliceIndex<[Base], Output = [Base]>,
@@ -65,3 +96,20 @@ impl Index<usize> for Sequence {
         &self.bases[index]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reverse_complement() {
+        assert_eq!(
+            Sequence::from("AACGTT").reverse_complement(),
+            Sequence::from("AACGTT")
+        );
+        assert_eq!(
+            Sequence::from("AAGCT").reverse_complement(),
+            Sequence::from("AGCTT")
+        );
+    }
+}
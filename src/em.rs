@@ -0,0 +1,472 @@
+//! Expectation maximization refinement of motifs, as done by MEME. Starting from a seed matrix,
+//! the probability of each window being a site and the base probabilities of the motif are
+//! updated in turn until the likelihood of the sequences stops improving.
+
+use crate::{
+    datastructures::{
+        background::Background,
+        pfm::Pfm,
+        pwm::Pwm,
+        sequence::{Sequence, Strand},
+    },
+    motif_finder::{Site, SiteModel},
+    Error, Result,
+};
+
+/// Tunables for the refinement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmConfig {
+    /// How many sites each sequence has
+    pub model: SiteModel,
+    /// Iterations run before giving up on convergence
+    pub max_iterations: usize,
+    /// Change in log-likelihood below which the refinement has converged
+    pub tolerance: f64,
+    /// Count added to every base at each position, so no base becomes impossible
+    pub pseudocount: f64,
+}
+
+impl Default for EmConfig {
+    fn default() -> Self {
+        Self {
+            model: SiteModel::Oops,
+            max_iterations: 100,
+            tolerance: 1e-6,
+            pseudocount: 0.1,
+        }
+    }
+}
+
+impl EmConfig {
+    /// Checks that the tunables make sense together.
+    pub fn validate(&self) -> Result<()> {
+        if self.max_iterations == 0 {
+            return Err(Error::InvalidConfig(
+                "max iterations must be at least 1".to_string(),
+            ));
+        }
+        if !(self.tolerance.is_finite() && self.tolerance >= 0.0) {
+            return Err(Error::InvalidConfig(format!(
+                "tolerance must be a non-negative number, got {}",
+                self.tolerance
+            )));
+        }
+        // A base with no count gets a log probability of minus infinity, and NaN posteriors
+        if !(self.pseudocount.is_finite() && self.pseudocount > 0.0) {
+            return Err(Error::InvalidConfig(format!(
+                "pseudocount must be a positive number, got {}",
+                self.pseudocount
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// A motif refined by [`refine`].
+#[derive(Debug, Clone)]
+pub struct Refinement {
+    /// Base probabilities at each position of the motif
+    pub ppm: Pwm,
+    /// Natural log of the likelihood of the sequences under the motif model over their likelihood
+    /// under the background alone
+    pub log_likelihood: f64,
+    /// Sum of the site probabilities of every window
+    pub expected_sites: f64,
+    /// The most likely sites: the best window of each sequence with [`SiteModel::Oops`], the best
+    /// window of each sequence likely to have a site with [`SiteModel::Zoops`], and every window
+    /// likely to be a site that does not overlap an earlier one with [`SiteModel::Tcm`]
+    pub sites: Vec<Site>,
+    pub iterations: usize,
+    pub converged: bool,
+}
+
+/// A window of one strand of a sequence, with its log likelihood under the background.
+struct Window {
+    sequence: usize,
+    strand: Strand,
+    /// Start in the strand, so in the reverse complement for the reverse strand
+    start: usize,
+    background: f64,
+}
+
+/// Refines `seed` on `seqs` by expectation maximization, keeping its width. Windows are scored
+/// against `background`, on both strands if `both_strands` is set.
+pub fn refine(
+    seed: &Pfm,
+    seqs: &[Sequence],
+    background: &Background,
+    both_strands: bool,
+    config: &EmConfig,
+) -> Result<Refinement> {
+    config.validate()?;
+    if seed.is_empty() {
+        return Err(Error::InvalidConfig("the seed motif is empty".to_string()));
+    }
+    let width = seed.len();
+
+    let strands = seqs
+        .iter()
+        .map(|seq| {
+            let mut strands = vec![(Strand::Forward, seq.clone())];
+            if both_strands {
+                strands.push((Strand::Reverse, seq.reverse_complement()));
+            }
+            strands
+        })
+        .collect::<Vec<_>>();
+
+    // Windows grouped by sequence, as the models only differ in how they are spread over those
+    let windows = strands
+        .iter()
+        .enumerate()
+        .map(|(i, strands)| {
+            strands
+                .iter()
+                .flat_map(|(strand, seq)| {
+                    (0..(seq.len() + 1).saturating_sub(width)).map(move |start| Window {
+                        sequence: i,
+                        strand: *strand,
+                        start,
+                        background: background.window_log_likelihood(seq, start, width)
+                            * std::f64::consts::LN_2,
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .filter(|windows| !windows.is_empty())
+        .collect::<Vec<_>>();
+
+    if windows.is_empty() {
+        return Err(Error::EmptyInput(format!(
+            "no sequence is at least as long as the motif ({})",
+            width
+        )));
+    }
+    let strand_seq = |window: &Window| match window.strand {
+        Strand::Forward => &strands[window.sequence][0].1,
+        Strand::Reverse => &strands[window.sequence][1].1,
+    };
+
+    let total_windows = windows.iter().map(Vec::len).sum::<usize>();
+    // Prior probability of a sequence having a site for ZOOPS, and of a window being one for TCM
+    let mut prior = match config.model {
+        SiteModel::Oops => 1.0,
+        SiteModel::Zoops => 0.5,
+        SiteModel::Tcm => windows.len() as f64 / total_windows as f64,
+    };
+
+    let mut ppm = smoothed(
+        seed.matrix
+            .iter()
+            .map(|row| row.map(|count| count as f64))
+            .collect(),
+        config.pseudocount,
+    );
+    let mut log_likelihood = f64::NEG_INFINITY;
+    let mut posteriors = Vec::new();
+    let mut iterations = 0;
+    let mut converged = false;
+
+    while iterations < config.max_iterations {
+        iterations += 1;
+
+        // E-step: the probability of each window being a site
+        let log_ppm = ppm.iter().map(|row| row.map(f64::ln)).collect::<Vec<_>>();
+        let mut next_log_likelihood = 0.0;
+        posteriors = windows
+            .iter()
+            .map(|windows| {
+                let ratios = windows
+                    .iter()
+                    .map(|window| {
+                        let seq = strand_seq(window);
+                        (0..width)
+                            .map(|k| log_ppm[k][seq[window.start + k].to_index()])
+                            .sum::<f64>()
+                            - window.background
+                    })
+                    .collect::<Vec<_>>();
+
+                let (posteriors, log_likelihood) = e_step(&ratios, config.model, prior);
+                next_log_likelihood += log_likelihood;
+                posteriors
+            })
+            .collect::<Vec<_>>();
+
+        // M-step: the base probabilities and prior that make those sites most likely
+        let mut counts = vec![[0.0; 4]; width];
+        for (windows, posteriors) in windows.iter().zip(posteriors.iter()) {
+            for (window, &z) in windows.iter().zip(posteriors) {
+                let seq = strand_seq(window);
+                for (k, row) in counts.iter_mut().enumerate() {
+                    row[seq[window.start + k].to_index()] += z;
+                }
+            }
+        }
+        ppm = smoothed(counts, config.pseudocount);
+
+        let expected_sites = posteriors.iter().flatten().sum::<f64>();
+        // Kept away from 0 and 1, which could never be left again
+        prior = match config.model {
+            SiteModel::Oops => 1.0,
+            SiteModel::Zoops => (expected_sites / windows.len() as f64).clamp(1e-6, 1.0 - 1e-6),
+            SiteModel::Tcm => (expected_sites / total_windows as f64).clamp(1e-6, 1.0 - 1e-6),
+        };
+
+        let change = (next_log_likelihood - log_likelihood).abs();
+        log_likelihood = next_log_likelihood;
+        if change < config.tolerance {
+            converged = true;
+            break;
+        }
+    }
+
+    let mut sites = Vec::new();
+    for (windows, posteriors) in windows.iter().zip(posteriors.iter()) {
+        let best = (0..windows.len())
+            .max_by(|&a, &b| posteriors[a].partial_cmp(&posteriors[b]).unwrap())
+            .unwrap();
+        let picked = match config.model {
+            SiteModel::Oops => vec![best],
+            SiteModel::Zoops if posteriors.iter().sum::<f64>() >= 0.5 => vec![best],
+            SiteModel::Zoops => Vec::new(),
+            SiteModel::Tcm => non_overlapping_sites(windows, posteriors, &strands, width),
+        };
+
+        sites.extend(picked.into_iter().map(|i| {
+            let window = &windows[i];
+            let len = strand_seq(window).len();
+            Site {
+                sequence: window.sequence,
+                start: match window.strand {
+                    Strand::Forward => window.start,
+                    Strand::Reverse => len - window.start - width,
+                },
+                strand: window.strand,
+            }
+        }));
+    }
+
+    Ok(Refinement {
+        ppm: Pwm {
+            matrix: ppm,
+            sample_size: seed.sample_size,
+        },
+        log_likelihood,
+        expected_sites: posteriors.iter().flatten().sum(),
+        sites,
+        iterations,
+        converged,
+    })
+}
+
+/// Site probabilities of the windows of one sequence, given the natural log of the likelihood
+/// ratio of each window being a site, and the log likelihood ratio of the sequence.
+fn e_step(ratios: &[f64], model: SiteModel, prior: f64) -> (Vec<f64>, f64) {
+    let max = ratios.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let windows = ratios.len() as f64;
+
+    match model {
+        SiteModel::Oops | SiteModel::Zoops => {
+            // The sequence has a site in one of its windows, each equally likely up front, or in
+            // ZOOPS no site at all. Summed in log space, as the ratios can be far beyond the
+            // range of e^x in either direction.
+            let scaled = ratios.iter().map(|r| (r - max).exp()).collect::<Vec<_>>();
+            let site = (prior / windows).ln() + max + scaled.iter().sum::<f64>().ln();
+            let none = (1.0 - prior).ln();
+            let top = site.max(none);
+            let total = top + ((site - top).exp() + (none - top).exp()).ln();
+
+            (
+                scaled
+                    .iter()
+                    .map(|s| prior / windows * s * (max - total).exp())
+                    .collect(),
+                total,
+            )
+        }
+        SiteModel::Tcm => ratios
+            .iter()
+            .map(|&r| {
+                // Each window is a site on its own, with probability prior
+                let site = prior.ln() + r;
+                let none = (1.0 - prior).ln();
+                let top = site.max(none);
+                let total = top + ((site - top).exp() + (none - top).exp()).ln();
+                ((site - total).exp(), total)
+            })
+            .fold((Vec::new(), 0.0), |(mut posteriors, sum), (z, total)| {
+                posteriors.push(z);
+                (posteriors, sum + total)
+            }),
+    }
+}
+
+/// Windows likely to be a site, skipping those that overlap a more likely site.
+fn non_overlapping_sites(
+    windows: &[Window],
+    posteriors: &[f64],
+    strands: &[Vec<(Strand, Sequence)>],
+    width: usize,
+) -> Vec<usize> {
+    let forward_start = |window: &Window| match window.strand {
+        Strand::Forward => window.start,
+        Strand::Reverse => strands[window.sequence][1].1.len() - window.start - width,
+    };
+
+    let mut candidates = (0..windows.len())
+        .filter(|&i| posteriors[i] >= 0.5)
+        .collect::<Vec<_>>();
+    candidates.sort_by(|&a, &b| posteriors[b].partial_cmp(&posteriors[a]).unwrap());
+
+    let mut picked: Vec<usize> = Vec::new();
+    for i in candidates {
+        let start = forward_start(&windows[i]);
+        if picked
+            .iter()
+            .all(|&j| forward_start(&windows[j]).abs_diff(start) >= width)
+        {
+            picked.push(i);
+        }
+    }
+
+    picked.sort_by_key(|&i| forward_start(&windows[i]));
+    picked
+}
+
+/// Base probabilities from expected counts, with `pseudocount` added to every count.
+fn smoothed(counts: Vec<[f64; 4]>, pseudocount: f64) -> Vec<[f64; 4]> {
+    counts
+        .into_iter()
+        .map(|row| {
+            let total = row.iter().sum::<f64>() + 4.0 * pseudocount;
+            row.map(|count| (count + pseudocount) / total)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seqs() -> Vec<Sequence> {
+        [
+            "TTGACAGATTACAGCTTAGCAT",
+            "CCATGATTACAGTTGCAGCA",
+            "ATGATTACAGGGCATCGTAC",
+            "GCTAGCTAGATTACAGTCCA",
+        ]
+        .into_iter()
+        .map(Sequence::from)
+        .collect()
+    }
+
+    #[test]
+    fn test_em_config_validate() {
+        assert!(EmConfig::default().validate().is_ok());
+        for config in [
+            EmConfig {
+                pseudocount: 0.0,
+                ..Default::default()
+            },
+            EmConfig {
+                max_iterations: 0,
+                ..Default::default()
+            },
+        ] {
+            let seed = Pfm::from_sequences(&[Sequence::from("GATTACA")]);
+            let err = refine(&seed, &seqs(), &Background::uniform(), false, &config);
+            assert!(matches!(err, Err(Error::InvalidConfig(_))), "{:?}", config);
+        }
+    }
+
+    #[test]
+    fn test_e_step_with_tiny_ratios() {
+        let ratios = [-800.0, -801.0, -802.0];
+
+        let (posteriors, log_likelihood) = e_step(&ratios, SiteModel::Oops, 1.0);
+        assert!((posteriors.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(posteriors[0] > posteriors[1] && posteriors[1] > posteriors[2]);
+        assert!(log_likelihood.is_finite() && log_likelihood < -800.0);
+
+        let (posteriors, log_likelihood) = e_step(&ratios, SiteModel::Zoops, 0.5);
+        assert!(posteriors.iter().all(|&z| z.is_finite() && z < 1e-300));
+        assert!((log_likelihood - 0.5f64.ln()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_refine_fixes_a_poor_seed() {
+        // One mismatch in the seed, which the sites correct
+        let seed = Pfm::from_sequences(&[Sequence::from("GATTCCA")]);
+
+        let refinement = refine(
+            &seed,
+            &seqs(),
+            &Background::uniform(),
+            false,
+            &EmConfig::default(),
+        )
+        .unwrap();
+
+        assert!(refinement.converged);
+        assert_eq!(refinement.ppm.get_consensus_string(), "GATTACA");
+        assert_eq!(
+            refinement
+                .sites
+                .iter()
+                .map(|site| site.start)
+                .collect::<Vec<_>>(),
+            vec![6, 4, 2, 8]
+        );
+        assert!((refinement.expected_sites - 4.0).abs() < 1e-9);
+        assert!(refinement.log_likelihood > 0.0);
+    }
+
+    #[test]
+    fn test_refine_zoops_leaves_out_sequence() {
+        let mut seqs = seqs();
+        seqs.push(Sequence::from("GCGCGGCCGCGCGCCGCGCG"));
+        let seed = Pfm::from_sequences(&[Sequence::from("GATTACA")]);
+        let config = EmConfig {
+            model: SiteModel::Zoops,
+            ..Default::default()
+        };
+
+        let refinement = refine(&seed, &seqs, &Background::uniform(), true, &config).unwrap();
+
+        assert_eq!(
+            refinement
+                .sites
+                .iter()
+                .map(|site| (site.sequence, site.strand))
+                .collect::<Vec<_>>(),
+            (0..4).map(|i| (i, Strand::Forward)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_refine_tcm_finds_every_site() {
+        let seqs = [Sequence::from("GATTACATTTTGATTACAGGGGTGTAATCCC")];
+        let seed = Pfm::from_sequences(&[Sequence::from("GATTACA")]);
+        let config = EmConfig {
+            model: SiteModel::Tcm,
+            ..Default::default()
+        };
+
+        let refinement = refine(&seed, &seqs, &Background::uniform(), true, &config).unwrap();
+
+        assert_eq!(
+            refinement
+                .sites
+                .iter()
+                .map(|site| (site.start, site.strand))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, Strand::Forward),
+                (11, Strand::Forward),
+                (22, Strand::Reverse)
+            ]
+        );
+    }
+}
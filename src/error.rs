@@ -0,0 +1,46 @@
+//! The error type shared by the whole crate.
+
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file failed
+    Io(io::Error),
+    /// The input is malformed, `line` is 1-indexed
+    Parse { line: usize, message: String },
+    /// There is nothing to work with in the input
+    EmptyInput(String),
+    /// The given options do not make sense together
+    InvalidConfig(String),
+    /// Drawing a sequence logo failed
+    Plot(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            Error::EmptyInput(message) => write!(f, "empty input: {}", message),
+            Error::InvalidConfig(message) => write!(f, "invalid configuration: {}", message),
+            Error::Plot(message) => write!(f, "could not plot: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
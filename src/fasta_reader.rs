@@ -1,29 +1,538 @@
+//! Reading of fasta files.
+//!
+//! Inputs can be plain text, gzip or BGZF compressed, the compression is detected from the first
+//! bytes of the input. The path `-` reads from stdin.
+//!
+//! `N`, other IUPAC ambiguity codes and, if asked for, lowercase soft-masked bases are never
+//! searched. They split a record into [`Segment`]s, so no motif can span over them. Characters
+//! that are neither, like `*` or `-`, are dropped and split the record the same way.
+
 use std::{
     fs::File,
-    io::{BufReader, Read},
+    io::{self, BufRead, BufReader},
     path::Path,
 };
 
-use crate::datastructures::{base::Base, sequence::Sequence};
+use flate2::bufread::MultiGzDecoder;
+
+use crate::{
+    datastructures::{base::Base, sequence::Sequence},
+    fastq_reader::FastqReader,
+    Error, Result,
+};
+
+/// Options for how records are parsed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Treat lowercase bases as soft-masked repeats, masking them like `N`
+    pub soft_mask: bool,
+    /// Mask fastq positions with a lower Phred quality than this
+    pub min_quality: Option<u8>,
+}
+
+/// A single entry of a fasta or fastq file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FastaRecord {
+    /// The header up to the first whitespace
+    pub id: String,
+    /// The rest of the header, without surrounding whitespace
+    pub description: String,
+    /// The unmasked stretches of the record, in order
+    pub segments: Vec<Segment>,
+    /// Number of positions in the record, masked ones included
+    pub length: usize,
+    /// Number of characters that were neither bases nor ambiguity codes, and were removed. Each
+    /// one ends the segment before it.
+    pub dropped: usize,
+}
+
+/// An unmasked stretch of a record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    /// Position of the first base of the segment in the record
+    pub offset: usize,
+    pub sequence: Sequence,
+}
+
+impl FastaRecord {
+    /// Number of positions that were masked.
+    pub fn masked(&self) -> usize {
+        self.length
+            - self
+                .segments
+                .iter()
+                .map(|segment| segment.sequence.len())
+                .sum::<usize>()
+    }
+}
+
+/// Builds the segments of a record one character at a time.
+#[derive(Debug, Default)]
+pub(crate) struct RecordBuilder {
+    segments: Vec<Segment>,
+    bases: Vec<Base>,
+    length: usize,
+    dropped: usize,
+}
+
+impl RecordBuilder {
+    pub(crate) fn push(&mut self, c: u8, options: &ParseOptions) {
+        if c.is_ascii_whitespace() {
+            return;
+        }
+
+        if options.soft_mask && c.is_ascii_lowercase() && is_nucleotide_code(c) {
+            self.mask();
+            return;
+        }
+
+        match Base::try_from(char::from(c).to_ascii_uppercase()) {
+            Ok(base) => {
+                self.bases.push(base);
+                self.length += 1;
+            }
+            Err(_) if is_nucleotide_code(c) => self.mask(),
+            Err(_) => {
+                self.end_segment();
+                self.dropped += 1;
+            }
+        }
+    }
+
+    /// Adds a masked position, ending the current segment.
+    pub(crate) fn mask(&mut self) {
+        self.end_segment();
+        self.length += 1;
+    }
+
+    fn end_segment(&mut self) {
+        if !self.bases.is_empty() {
+            let bases = std::mem::take(&mut self.bases);
+            self.segments.push(Segment {
+                offset: self.length - bases.len(),
+                sequence: Sequence::from(bases),
+            });
+        }
+    }
+
+    pub(crate) fn finish(mut self, (header_line, header): (usize, String)) -> Result<FastaRecord> {
+        self.end_segment();
+
+        if self.length == 0 {
+            return Err(Error::Parse {
+                line: header_line,
+                message: "header without a sequence".to_string(),
+            });
+        }
+
+        let header = header.trim();
+        let (id, description) = header
+            .split_once(char::is_whitespace)
+            .unwrap_or((header, ""));
+
+        Ok(FastaRecord {
+            id: id.to_string(),
+            description: description.trim().to_string(),
+            segments: self.segments,
+            length: self.length,
+            dropped: self.dropped,
+        })
+    }
+}
+
+/// A, C, G, T or one of the IUPAC ambiguity codes, in either case.
+fn is_nucleotide_code(c: u8) -> bool {
+    matches!(
+        c.to_ascii_uppercase(),
+        b'A' | b'C'
+            | b'G'
+            | b'T'
+            | b'R'
+            | b'Y'
+            | b'S'
+            | b'W'
+            | b'K'
+            | b'M'
+            | b'B'
+            | b'D'
+            | b'H'
+            | b'V'
+            | b'N'
+    )
+}
+
+/// Compression of an input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    /// Blocked gzip, as written by `bgzip`
+    Bgzf,
+}
+
+impl Compression {
+    /// Detects the compression from the magic bytes at the start of an input.
+    pub fn detect(bytes: &[u8]) -> Self {
+        if !bytes.starts_with(&[0x1f, 0x8b]) {
+            return Compression::None;
+        }
+
+        // BGZF blocks are gzip members with the FEXTRA flag set and a "BC" extra subfield
+        let has_extra_field = bytes.get(3).is_some_and(|flags| flags & 0x04 != 0);
+        if has_extra_field && bytes.get(12..14) == Some(b"BC") {
+            Compression::Bgzf
+        } else {
+            Compression::Gzip
+        }
+    }
+}
+
+/// Opens `path` for buffered reading, decompressing it if needed. The path `-` reads from stdin.
+pub fn open_input(path: &Path) -> Result<Box<dyn BufRead>> {
+    if path == Path::new("-") {
+        return decompress(io::stdin().lock());
+    }
 
-pub fn read_fasta(path: &Path) -> Vec<Sequence> {
     let file = File::open(path)
-        .unwrap_or_else(|_| panic!("Could not opne file at path: {}", path.to_str().unwrap()));
-
-    let mut reader = BufReader::new(file);
-    let mut file_contens = String::new();
-    reader.read_to_string(&mut file_contens).unwrap();
-
-    file_contens
-        .split(">")
-        .skip(1)
-        .map(|seq| {
-            seq.split_once("\n")
-                .unwrap()
-                .1
-                .chars()
-                .filter_map(|c| Base::try_from(c.to_ascii_uppercase()).ok())
-                .collect()
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+
+    decompress(BufReader::new(file))
+}
+
+/// Wraps `reader` in a decoder if it starts with gzip or BGZF magic bytes.
+pub fn decompress<'a>(mut reader: impl BufRead + 'a) -> Result<Box<dyn BufRead + 'a>> {
+    match Compression::detect(reader.fill_buf()?) {
+        Compression::None => Ok(Box::new(reader)),
+        // BGZF files are a series of gzip members, so both are read by a multi member decoder
+        Compression::Gzip | Compression::Bgzf => {
+            Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+        }
+    }
+}
+
+/// Reads every record in the fasta file at `path`, see [`open_input`] for the supported inputs.
+pub fn read_fasta(path: &Path, options: ParseOptions) -> Result<Vec<FastaRecord>> {
+    let records =
+        FastaReader::with_options(open_input(path)?, options).collect::<Result<Vec<_>>>()?;
+
+    non_empty(records, path)
+}
+
+/// Reads every record in the fasta or fastq file at `path`, telling the formats apart by the
+/// first character of the file.
+pub fn read_sequences(path: &Path, options: ParseOptions) -> Result<Vec<FastaRecord>> {
+    let mut input = open_input(path)?;
+
+    let is_fastq = input
+        .fill_buf()?
+        .iter()
+        .find(|c| !c.is_ascii_whitespace())
+        .is_some_and(|&c| c == b'@');
+
+    let records = if is_fastq {
+        FastqReader::with_options(input, options).collect::<Result<Vec<_>>>()?
+    } else {
+        FastaReader::with_options(input, options).collect::<Result<Vec<_>>>()?
+    };
+
+    non_empty(records, path)
+}
+
+pub(crate) fn non_empty(records: Vec<FastaRecord>, path: &Path) -> Result<Vec<FastaRecord>> {
+    if records.is_empty() {
+        return Err(Error::EmptyInput(format!(
+            "no sequences found in {}",
+            path.display()
+        )));
+    }
+
+    Ok(records)
+}
+
+/// Reads an input one line at a time, reusing the line buffer.
+pub(crate) struct LineReader<R> {
+    reader: R,
+    /// The last line read, without the line ending
+    pub(crate) line: Vec<u8>,
+    pub(crate) line_number: usize,
+}
+
+impl<R: BufRead> LineReader<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: Vec::new(),
+            line_number: 0,
+        }
+    }
+
+    /// Reads the next line into `self.line`. Returns false at the end of the input.
+    pub(crate) fn read_line(&mut self) -> Result<bool> {
+        self.line.clear();
+        if self.reader.read_until(b'\n', &mut self.line)? == 0 {
+            return Ok(false);
+        }
+
+        self.line_number += 1;
+        while let Some(b'\n' | b'\r') = self.line.last() {
+            self.line.pop();
+        }
+
+        Ok(true)
+    }
+
+    /// Line number and text of the current line, if it starts with `prefix`.
+    pub(crate) fn header(&self, prefix: u8) -> Option<(usize, String)> {
+        self.line.strip_prefix(&[prefix]).map(|header| {
+            (
+                self.line_number,
+                String::from_utf8_lossy(header).into_owned(),
+            )
         })
-        .collect()
+    }
+}
+
+/// Streaming fasta parser, yielding one record at a time so only the record being read is kept
+/// in memory.
+///
+/// ```
+/// use de_novo_motif_finder::fasta_reader::FastaReader;
+///
+/// let input = ">seq1 first\nACGT\n>seq2\nTTTT\n";
+/// let ids = FastaReader::new(input.as_bytes())
+///     .map(|record| record.map(|record| record.id))
+///     .collect::<Result<Vec<_>, _>>()?;
+///
+/// assert_eq!(ids, ["seq1", "seq2"]);
+/// # Ok::<(), de_novo_motif_finder::Error>(())
+/// ```
+pub struct FastaReader<R> {
+    lines: LineReader<R>,
+    /// Line number and text of the header of the next record, found while reading the previous
+    next_header: Option<(usize, String)>,
+    done: bool,
+    options: ParseOptions,
+}
+
+impl<R: BufRead> FastaReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> Self {
+        Self {
+            lines: LineReader::new(reader),
+            next_header: None,
+            done: false,
+            options,
+        }
+    }
+
+    fn next_record(&mut self) -> Result<Option<FastaRecord>> {
+        let header = match self.next_header.take() {
+            Some(header) => header,
+            None => loop {
+                if !self.lines.read_line()? {
+                    return Ok(None);
+                }
+
+                if let Some(header) = self.lines.header(b'>') {
+                    break header;
+                }
+
+                if !self.lines.line.trim_ascii().is_empty() {
+                    return Err(Error::Parse {
+                        line: self.lines.line_number,
+                        message: "sequence data before the first header".to_string(),
+                    });
+                }
+            },
+        };
+
+        let mut record = RecordBuilder::default();
+        while self.lines.read_line()? {
+            if let Some(header) = self.lines.header(b'>') {
+                self.next_header = Some(header);
+                break;
+            }
+
+            for &c in self.lines.line.iter() {
+                record.push(c, &self.options);
+            }
+        }
+
+        record.finish(header).map(Some)
+    }
+}
+
+impl<R: BufRead> Iterator for FastaReader<R> {
+    type Item = Result<FastaRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let record = self.next_record().transpose();
+        if !matches!(record, Some(Ok(_))) {
+            self.done = true;
+        }
+
+        record
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_fasta(input: &str) -> Result<Vec<FastaRecord>> {
+        FastaReader::new(input.as_bytes()).collect()
+    }
+
+    fn sequences(record: &FastaRecord) -> Vec<(usize, Sequence)> {
+        record
+            .segments
+            .iter()
+            .map(|segment| (segment.offset, segment.sequence.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_fasta_multiline() {
+        let records = parse_fasta(">seq1 first\nACGT\nacgt\n>seq2\r\nTTTT\r\n").unwrap();
+
+        assert_eq!(
+            sequences(&records[0]),
+            vec![(0, Sequence::from("ACGTACGT"))]
+        );
+        assert_eq!(sequences(&records[1]), vec![(0, Sequence::from("TTTT"))]);
+    }
+
+    #[test]
+    fn test_parse_fasta_header() {
+        let records = parse_fasta(">chr1:100-200  peak 1 of 2 \nACGT\n>seq2\nACGT\n").unwrap();
+
+        assert_eq!(records[0].id, "chr1:100-200");
+        assert_eq!(records[0].description, "peak 1 of 2");
+        assert_eq!(records[1].id, "seq2");
+        assert_eq!(records[1].description, "");
+    }
+
+    #[test]
+    fn test_parse_fasta_header_without_sequence() {
+        let err = parse_fasta(">seq1\nACGT\n>seq2").unwrap_err();
+
+        assert!(matches!(err, Error::Parse { line: 3, .. }), "{:?}", err);
+    }
+
+    #[test]
+    fn test_parse_fasta_data_before_header() {
+        let err = parse_fasta("ACGT\n>seq1\nACGT\n").unwrap_err();
+
+        assert!(matches!(err, Error::Parse { line: 1, .. }), "{:?}", err);
+    }
+
+    #[test]
+    fn test_parse_fasta_greater_than_in_description() {
+        let records = parse_fasta(">seq1 a > b\nACGT\n>seq2\nTTTT\n").unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].description, "a > b");
+        assert_eq!(sequences(&records[0]), vec![(0, Sequence::from("ACGT"))]);
+    }
+
+    #[test]
+    fn test_fasta_reader_yields_records_before_error() {
+        let mut reader = FastaReader::new(">seq1\nAC\nGT\n>seq2".as_bytes());
+
+        assert_eq!(reader.next().unwrap().unwrap().length, 4);
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_compression_detect() {
+        assert_eq!(Compression::detect(b">seq1\nACGT\n"), Compression::None);
+        assert_eq!(
+            Compression::detect(&[0x1f, 0x8b, 0x08, 0x00, 0, 0, 0, 0, 0, 0xff]),
+            Compression::Gzip
+        );
+        assert_eq!(
+            Compression::detect(&[
+                0x1f, 0x8b, 0x08, 0x04, 0, 0, 0, 0, 0, 0xff, 0x06, 0x00, b'B', b'C', 0x02, 0x00
+            ]),
+            Compression::Bgzf
+        );
+    }
+
+    #[test]
+    fn test_decompress_multiple_gzip_members() {
+        use flate2::{write::GzEncoder, Compression as Level};
+        use std::io::Write;
+
+        let mut compressed = Vec::new();
+        for member in [">seq1\nACGT\n", ">seq2\nTTTT\n"] {
+            let mut encoder = GzEncoder::new(Vec::new(), Level::default());
+            encoder.write_all(member.as_bytes()).unwrap();
+            compressed.extend(encoder.finish().unwrap());
+        }
+
+        let records = FastaReader::new(decompress(compressed.as_slice()).unwrap())
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(sequences(&records[1]), vec![(0, Sequence::from("TTTT"))]);
+    }
+
+    #[test]
+    fn test_ambiguity_codes_split_record() {
+        let records = parse_fasta(">seq1\nACGTNNAC\nRTT*GG-A\n").unwrap();
+
+        assert_eq!(
+            sequences(&records[0]),
+            vec![
+                (0, Sequence::from("ACGT")),
+                (6, Sequence::from("AC")),
+                (9, Sequence::from("TT")),
+                (11, Sequence::from("GG")),
+                (13, Sequence::from("A"))
+            ]
+        );
+        assert_eq!(records[0].length, 14);
+        assert_eq!(records[0].masked(), 3);
+        assert_eq!(records[0].dropped, 2);
+    }
+
+    #[test]
+    fn test_soft_mask() {
+        let input = ">seq1\nACGTacgtTT\n";
+
+        let records = parse_fasta(input).unwrap();
+        assert_eq!(
+            sequences(&records[0]),
+            vec![(0, Sequence::from("ACGTACGTTT"))]
+        );
+
+        let options = ParseOptions {
+            soft_mask: true,
+            ..Default::default()
+        };
+        let records = FastaReader::with_options(input.as_bytes(), options)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            sequences(&records[0]),
+            vec![(0, Sequence::from("ACGT")), (8, Sequence::from("TT"))]
+        );
+    }
+
+    #[test]
+    fn test_record_of_only_masked_positions() {
+        let records = parse_fasta(">seq1\nNNNN\n").unwrap();
+
+        assert!(records[0].segments.is_empty());
+        assert_eq!(records[0].masked(), 4);
+    }
 }
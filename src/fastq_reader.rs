@@ -0,0 +1,202 @@
+//! Reading of fastq files.
+//!
+//! Records are parsed into the same [`FastaRecord`] as fasta records, with positions below
+//! [`ParseOptions::min_quality`] masked so the search never uses them.
+
+use std::{io::BufRead, path::Path};
+
+use crate::{
+    fasta_reader::{non_empty, open_input, FastaRecord, LineReader, ParseOptions, RecordBuilder},
+    Error, Result,
+};
+
+/// Offset of the Phred quality characters
+const PHRED_OFFSET: u8 = 33;
+
+/// Reads every record in the fastq file at `path`, see
+/// [`open_input`](crate::fasta_reader::open_input) for the supported inputs.
+pub fn read_fastq(path: &Path, options: ParseOptions) -> Result<Vec<FastaRecord>> {
+    let records =
+        FastqReader::with_options(open_input(path)?, options).collect::<Result<Vec<_>>>()?;
+
+    non_empty(records, path)
+}
+
+/// Streaming fastq parser for records of four lines: header, sequence, separator and quality.
+///
+/// ```
+/// use de_novo_motif_finder::{fasta_reader::ParseOptions, fastq_reader::FastqReader};
+///
+/// let input = "@read1\nACGTACGT\n+\nIIII##II\n";
+/// let options = ParseOptions {
+///     min_quality: Some(20),
+///     ..Default::default()
+/// };
+/// let record = FastqReader::with_options(input.as_bytes(), options)
+///     .next()
+///     .unwrap()?;
+///
+/// assert_eq!(record.segments.len(), 2);
+/// assert_eq!(record.masked(), 2);
+/// # Ok::<(), de_novo_motif_finder::Error>(())
+/// ```
+pub struct FastqReader<R> {
+    lines: LineReader<R>,
+    done: bool,
+    options: ParseOptions,
+}
+
+impl<R: BufRead> FastqReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> Self {
+        Self {
+            lines: LineReader::new(reader),
+            done: false,
+            options,
+        }
+    }
+
+    fn parse_error(&self, message: impl Into<String>) -> Error {
+        Error::Parse {
+            line: self.lines.line_number,
+            message: message.into(),
+        }
+    }
+
+    /// Reads the next line, failing with `message` at the end of the input.
+    fn expect_line(&mut self, message: &str) -> Result<()> {
+        if self.lines.read_line()? {
+            Ok(())
+        } else {
+            Err(self.parse_error(message))
+        }
+    }
+
+    fn next_record(&mut self) -> Result<Option<FastaRecord>> {
+        loop {
+            if !self.lines.read_line()? {
+                return Ok(None);
+            }
+
+            if !self.lines.line.trim_ascii().is_empty() {
+                break;
+            }
+        }
+
+        let header = self
+            .lines
+            .header(b'@')
+            .ok_or_else(|| self.parse_error("expected a header starting with '@'"))?;
+
+        self.expect_line("record ends before its sequence")?;
+        let bases = self.lines.line.clone();
+
+        self.expect_line("record ends before its '+' separator")?;
+        if !self.lines.line.starts_with(b"+") {
+            return Err(self.parse_error("expected a '+' separator"));
+        }
+
+        self.expect_line("record ends before its quality line")?;
+        let qualities = &self.lines.line;
+        if qualities.len() != bases.len() {
+            return Err(self.parse_error(format!(
+                "quality line has {} characters, but the sequence has {}",
+                qualities.len(),
+                bases.len()
+            )));
+        }
+
+        let mut record = RecordBuilder::default();
+        for (&c, &quality) in bases.iter().zip(qualities.iter()) {
+            let quality = quality
+                .checked_sub(PHRED_OFFSET)
+                .ok_or_else(|| self.parse_error("invalid quality character"))?;
+
+            if self
+                .options
+                .min_quality
+                .is_some_and(|min_quality| quality < min_quality)
+            {
+                record.mask();
+            } else {
+                record.push(c, &self.options);
+            }
+        }
+
+        record.finish(header).map(Some)
+    }
+}
+
+impl<R: BufRead> Iterator for FastqReader<R> {
+    type Item = Result<FastaRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let record = self.next_record().transpose();
+        if !matches!(record, Some(Ok(_))) {
+            self.done = true;
+        }
+
+        record
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datastructures::sequence::Sequence;
+
+    fn parse_fastq(input: &str, min_quality: Option<u8>) -> Result<Vec<FastaRecord>> {
+        let options = ParseOptions {
+            min_quality,
+            ..Default::default()
+        };
+
+        FastqReader::with_options(input.as_bytes(), options).collect()
+    }
+
+    #[test]
+    fn test_parse_fastq() {
+        let records = parse_fastq(
+            "@read1 lane 1\nACGT\n+read1\nIIII\n\n@read2\nGGCC\n+\n!!!!\n",
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].id, "read1");
+        assert_eq!(records[0].description, "lane 1");
+        assert_eq!(records[1].segments[0].sequence, Sequence::from("GGCC"));
+    }
+
+    #[test]
+    fn test_parse_fastq_min_quality() {
+        let records = parse_fastq("@read1\nACGTACGT\n+\nII#IIII#\n", Some(20)).unwrap();
+
+        let segments = &records[0].segments;
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].sequence, Sequence::from("AC"));
+        assert_eq!(segments[1].offset, 3);
+        assert_eq!(segments[1].sequence, Sequence::from("TACG"));
+    }
+
+    #[test]
+    fn test_parse_fastq_quality_length_mismatch() {
+        let err = parse_fastq("@read1\nACGT\n+\nIII\n", None).unwrap_err();
+
+        assert!(matches!(err, Error::Parse { line: 4, .. }), "{:?}", err);
+    }
+
+    #[test]
+    fn test_parse_fastq_missing_separator() {
+        let err = parse_fastq("@read1\nACGT\nIIII\n", None).unwrap_err();
+
+        assert!(matches!(err, Error::Parse { line: 3, .. }), "{:?}", err);
+    }
+}
@@ -0,0 +1,457 @@
+//! Gibbs sampling motif discovery (Lawrence et al. 1993), a stochastic alternative to the search
+//! in [`crate::motif_finder`] for sequences too long to align at every shift.
+//!
+//! Each run starts from a random site in every sequence. The site of one sequence at a time is
+//! then drawn again, with each window weighted by how well it matches the motif of the other
+//! sites, and every few rounds all sites are shifted together so a run can move from part of a
+//! motif to the whole of it.
+
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    datastructures::sequence::{Sequence, Strand},
+    motif_finder::{motif_key, result_from_sites, MotifResult, SearchConfig, Site, SiteModel},
+    Error, Result,
+};
+
+/// Tunables for the Gibbs sampler, the motif width is the min length of the [`SearchConfig`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GibbsConfig {
+    /// Independent runs from random sites, the best motifs of all runs are kept
+    pub restarts: usize,
+    /// Times the site of every sequence is drawn again in each run
+    pub iterations: usize,
+    /// Rounds of drawing sites between shifting all sites together, 0 never shifts
+    pub shift_interval: usize,
+    /// Most positions the sites are shifted by at once
+    pub max_shift: usize,
+    /// Count added to each column, spread over the bases by the background frequencies
+    pub pseudocount: f64,
+    /// Seed of the random number generator, the same seed gives the same motifs
+    pub seed: u64,
+}
+
+impl Default for GibbsConfig {
+    fn default() -> Self {
+        Self {
+            restarts: 20,
+            iterations: 100,
+            shift_interval: 5,
+            max_shift: 3,
+            pseudocount: 1.0,
+            seed: 0,
+        }
+    }
+}
+
+impl GibbsConfig {
+    /// Checks that the tunables make sense together.
+    pub fn validate(&self) -> Result<()> {
+        if self.restarts == 0 {
+            return Err(Error::InvalidConfig(
+                "restarts must be at least 1".to_string(),
+            ));
+        }
+        if self.iterations == 0 {
+            return Err(Error::InvalidConfig(
+                "iterations must be at least 1".to_string(),
+            ));
+        }
+        if !(self.pseudocount.is_finite() && self.pseudocount > 0.0) {
+            return Err(Error::InvalidConfig(format!(
+                "pseudocount must be a positive number, got {}",
+                self.pseudocount
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// The searched strands of one sequence, with the natural log of the background likelihood of
+/// each of their windows.
+struct Strands {
+    /// Index of the sequence in the input
+    sequence: usize,
+    strands: Vec<(Strand, Sequence, Vec<f64>)>,
+}
+
+/// A site while sampling, starting in the strand it is on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Placement {
+    strand: usize,
+    start: usize,
+}
+
+/// Searches `seqs` for motifs of `config.min_length` by Gibbs sampling, running
+/// `gibbs.restarts` times and returning at most `config.hits` of the best motifs, scored like
+/// the results of [`crate::motif_finder::motif_finder`]. Sequences shorter than the motif are
+/// left without a site. Every other sequence gets one, so only [`SiteModel::Oops`] is supported.
+pub fn gibbs_motif_finder(
+    seqs: &[Sequence],
+    config: &SearchConfig,
+    gibbs: &GibbsConfig,
+) -> Result<Vec<MotifResult>> {
+    config.validate()?;
+    gibbs.validate()?;
+    if config.model != SiteModel::Oops {
+        return Err(Error::InvalidConfig(format!(
+            "the Gibbs sampler only supports the oops model, got {:?}",
+            config.model
+        )));
+    }
+
+    let width = config.min_length;
+    let background = &config.background;
+    let sampled = seqs
+        .iter()
+        .enumerate()
+        .filter(|(_, seq)| seq.len() >= width)
+        .map(|(i, seq)| {
+            let mut strands = vec![(Strand::Forward, seq.clone())];
+            if config.both_strands {
+                strands.push((Strand::Reverse, seq.reverse_complement()));
+            }
+
+            Strands {
+                sequence: i,
+                strands: strands
+                    .into_iter()
+                    .map(|(strand, seq)| {
+                        let background = (0..=seq.len() - width)
+                            .map(|start| {
+                                background.window_log_likelihood(&seq, start, width)
+                                    * std::f64::consts::LN_2
+                            })
+                            .collect();
+                        (strand, seq, background)
+                    })
+                    .collect(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if sampled.len() < 2 {
+        return Err(Error::EmptyInput(format!(
+            "at least two sequences of the motif length ({}) are needed, got {}",
+            width,
+            sampled.len()
+        )));
+    }
+
+    let reverse_seqs = if config.both_strands {
+        seqs.iter().map(|seq| seq.reverse_complement()).collect()
+    } else {
+        Vec::new()
+    };
+
+    let mut rng = StdRng::seed_from_u64(gibbs.seed);
+    let mut best = HashMap::new();
+    for _ in 0..gibbs.restarts {
+        let placements = run(&sampled, width, config, gibbs, &mut rng);
+        let sites = sampled
+            .iter()
+            .zip(placements)
+            .map(|(strands, placement)| {
+                let (strand, seq, _) = &strands.strands[placement.strand];
+                Site {
+                    sequence: strands.sequence,
+                    start: match strand {
+                        Strand::Forward => placement.start,
+                        Strand::Reverse => seq.len() - placement.start - width,
+                    },
+                    strand: *strand,
+                }
+            })
+            .collect();
+
+        let result = result_from_sites(sites, width, seqs, &reverse_seqs, config);
+        let key = motif_key(&result.pfm, config.both_strands);
+        if best
+            .get(&key)
+            .is_none_or(|other: &MotifResult| result.score > other.score)
+        {
+            best.insert(key, result);
+        }
+    }
+
+    Ok(best
+        .into_values()
+        .sorted_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap()
+                .then_with(|| a.consensus.cmp(&b.consensus))
+        })
+        .take(config.hits)
+        .collect())
+}
+
+/// One run of the sampler, returning the best placements it came across.
+fn run(
+    sampled: &[Strands],
+    width: usize,
+    config: &SearchConfig,
+    gibbs: &GibbsConfig,
+    rng: &mut StdRng,
+) -> Vec<Placement> {
+    let pseudocounts = config
+        .background
+        .frequencies
+        .map(|frequency| gibbs.pseudocount * frequency);
+
+    let mut placements = sampled
+        .iter()
+        .map(|strands| {
+            let strand = rng.gen_range(0..strands.strands.len());
+            let start = rng.gen_range(0..strands.strands[strand].2.len());
+            Placement { strand, start }
+        })
+        .collect::<Vec<_>>();
+    let mut best = (
+        score(sampled, &placements, width, &pseudocounts),
+        placements.clone(),
+    );
+
+    for iteration in 1..=gibbs.iterations {
+        for i in 0..sampled.len() {
+            // The motif of every other site
+            let mut counts = vec![pseudocounts; width];
+            for (j, placement) in placements.iter().enumerate().filter(|(j, _)| *j != i) {
+                add_site(&mut counts, sampled, j, *placement, width);
+            }
+            let log_ppm = &log_probabilities(&counts);
+
+            let windows = sampled[i]
+                .strands
+                .iter()
+                .enumerate()
+                .flat_map(|(strand, (_, seq, background))| {
+                    background.iter().enumerate().map(move |(start, bg)| {
+                        let ratio = (0..width)
+                            .map(|k| log_ppm[k][seq[start + k].to_index()])
+                            .sum::<f64>()
+                            - bg;
+                        (Placement { strand, start }, ratio)
+                    })
+                })
+                .collect::<Vec<_>>();
+            let picked = sample(&windows.iter().map(|(_, r)| *r).collect::<Vec<_>>(), rng);
+            placements[i] = windows[picked].0;
+        }
+
+        if gibbs.shift_interval > 0 && iteration % gibbs.shift_interval == 0 {
+            placements = shift(
+                sampled,
+                placements,
+                width,
+                gibbs.max_shift,
+                &pseudocounts,
+                rng,
+            );
+        }
+
+        let score = score(sampled, &placements, width, &pseudocounts);
+        if score > best.0 {
+            best = (score, placements.clone());
+        }
+    }
+
+    best.1
+}
+
+/// Shifts every site by the same amount, drawn by how well the shifted sites make a motif.
+fn shift(
+    sampled: &[Strands],
+    placements: Vec<Placement>,
+    width: usize,
+    max_shift: usize,
+    pseudocounts: &[f64; 4],
+    rng: &mut StdRng,
+) -> Vec<Placement> {
+    let max_shift = max_shift as isize;
+    let shifted = (-max_shift..=max_shift)
+        .filter_map(|shift| {
+            sampled
+                .iter()
+                .zip(placements.iter())
+                .map(|(strands, placement)| {
+                    let start = placement.start.checked_add_signed(shift)?;
+                    (start < strands.strands[placement.strand].2.len()).then_some(Placement {
+                        start,
+                        ..*placement
+                    })
+                })
+                .collect::<Option<Vec<_>>>()
+        })
+        .collect::<Vec<_>>();
+
+    let scores = shifted
+        .iter()
+        .map(|placements| score(sampled, placements, width, pseudocounts))
+        .collect::<Vec<_>>();
+    let picked = sample(&scores, rng);
+
+    shifted.into_iter().nth(picked).unwrap()
+}
+
+/// Natural log of the likelihood ratio of the sites under the motif they make over the
+/// background.
+fn score(
+    sampled: &[Strands],
+    placements: &[Placement],
+    width: usize,
+    pseudocounts: &[f64; 4],
+) -> f64 {
+    let mut counts = vec![*pseudocounts; width];
+    for (i, placement) in placements.iter().enumerate() {
+        add_site(&mut counts, sampled, i, *placement, width);
+    }
+    let log_ppm = log_probabilities(&counts);
+
+    sampled
+        .iter()
+        .zip(placements)
+        .map(|(strands, placement)| {
+            let (_, seq, background) = &strands.strands[placement.strand];
+            (0..width)
+                .map(|k| log_ppm[k][seq[placement.start + k].to_index()])
+                .sum::<f64>()
+                - background[placement.start]
+        })
+        .sum()
+}
+
+fn add_site(
+    counts: &mut [[f64; 4]],
+    sampled: &[Strands],
+    i: usize,
+    placement: Placement,
+    width: usize,
+) {
+    let seq = &sampled[i].strands[placement.strand].1;
+    for (k, row) in counts.iter_mut().enumerate().take(width) {
+        row[seq[placement.start + k].to_index()] += 1.0;
+    }
+}
+
+fn log_probabilities(counts: &[[f64; 4]]) -> Vec<[f64; 4]> {
+    counts
+        .iter()
+        .map(|row| {
+            let total = row.iter().sum::<f64>();
+            row.map(|count| (count / total).ln())
+        })
+        .collect()
+}
+
+/// Draws an index with a probability proportional to the exponent of its weight.
+fn sample(log_weights: &[f64], rng: &mut StdRng) -> usize {
+    let max = log_weights
+        .iter()
+        .copied()
+        .fold(f64::NEG_INFINITY, f64::max);
+    let weights = log_weights
+        .iter()
+        .map(|w| (w - max).exp())
+        .collect::<Vec<_>>();
+
+    let mut target = rng.gen::<f64>() * weights.iter().sum::<f64>();
+    for (i, weight) in weights.iter().enumerate() {
+        if target < *weight {
+            return i;
+        }
+        target -= weight;
+    }
+
+    weights.len() - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datastructures::base::Base;
+
+    /// Random sequences with the reverse complement of `motif` planted in every other one.
+    fn planted(motif: &str, count: usize, len: usize) -> Vec<Sequence> {
+        let mut rng = StdRng::seed_from_u64(42);
+        let motif = Sequence::from(motif);
+
+        (0..count)
+            .map(|i| {
+                let mut seq = (0..len)
+                    .map(|_| Base::try_from(rng.gen_range(0..4usize)).unwrap())
+                    .collect::<Sequence>();
+                let site = if i % 2 == 0 {
+                    motif.clone()
+                } else {
+                    motif.reverse_complement()
+                };
+                let start = rng.gen_range(0..=len - motif.len());
+                seq.bases
+                    .splice(start..start + motif.len(), site.bases.iter().copied());
+                seq
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_gibbs_finds_planted_motif() {
+        let seqs = planted("TGACTCAG", 8, 80);
+        let config = SearchConfig {
+            hits: 1,
+            min_length: 8,
+            both_strands: true,
+            ..Default::default()
+        };
+
+        let results = gibbs_motif_finder(&seqs, &config, &GibbsConfig::default()).unwrap();
+
+        let consensus = &results[0].consensus;
+        assert!(
+            consensus == "TGACTCAG" || consensus == "CTGAGTCA",
+            "{:?}",
+            results
+        );
+        assert_eq!(results[0].sites.len(), seqs.len());
+    }
+
+    #[test]
+    fn test_gibbs_is_reproducible() {
+        let seqs = planted("GATTACA", 6, 60);
+        let config = SearchConfig {
+            hits: 3,
+            min_length: 7,
+            ..Default::default()
+        };
+        let gibbs = GibbsConfig {
+            restarts: 5,
+            seed: 7,
+            ..Default::default()
+        };
+
+        let first = gibbs_motif_finder(&seqs, &config, &gibbs).unwrap();
+        let second = gibbs_motif_finder(&seqs, &config, &gibbs).unwrap();
+
+        assert_eq!(
+            first.iter().map(|r| &r.sites).collect::<Vec<_>>(),
+            second.iter().map(|r| &r.sites).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_gibbs_rejects_zoops() {
+        let config = SearchConfig {
+            min_length: 7,
+            model: SiteModel::Zoops,
+            ..Default::default()
+        };
+
+        let err = gibbs_motif_finder(&planted("GATTACA", 4, 30), &config, &GibbsConfig::default());
+
+        assert!(matches!(err, Err(Error::InvalidConfig(_))), "{:?}", err);
+    }
+}
@@ -0,0 +1,56 @@
+//! De novo sequence motif finding in DNA sequences.
+//!
+//! The crate is split into a few modules that can be used on their own:
+//!
+//! - [`datastructures`]: bases, sequences and the position frequency/weight matrices built from
+//!   them.
+//! - [`em`]: expectation maximization refinement of found motifs.
+//! - [`fasta_reader`]: reading sequences from fasta files.
+//! - [`fastq_reader`]: reading sequences from fastq files.
+//! - [`gibbs`]: Gibbs sampling, a stochastic alternative to the motif search.
+//! - [`motif_finder`]: the motif search itself, configured through
+//!   [`motif_finder::SearchConfig`].
+//! - [`motif_formats`]: reading and writing motif files.
+//! - [`scanner`]: scanning sequences for sites of a known motif.
+//! - [`plot`]: sequence logos of found motifs.
+//! - [`stats`]: the statistical tests motifs are judged by.
+//! - [`words`]: counting words with mismatches to seed motifs from enriched ones.
+//!
+//! Fallible functions return the crate wide [`Result`], with [`Error`] describing what went wrong.
+//!
+//! ```
+//! use de_novo_motif_finder::{
+//!     datastructures::sequence::Sequence,
+//!     motif_finder::{motif_finder, SearchConfig},
+//! };
+//!
+//! let seqs = [
+//!     Sequence::from("GGTATAACCGGTTCA"),
+//!     Sequence::from("CAACCGGTTTGCA"),
+//!     Sequence::from("TTTGAACCGGTTG"),
+//! ];
+//! let config = SearchConfig {
+//!     hits: 1,
+//!     min_length: 4,
+//!     ..Default::default()
+//! };
+//!
+//! let results = motif_finder(&seqs, &config)?;
+//! assert_eq!(results[0].sites.len(), seqs.len());
+//! # Ok::<(), de_novo_motif_finder::Error>(())
+//! ```
+
+pub mod datastructures;
+pub mod em;
+pub mod error;
+pub mod fasta_reader;
+pub mod fastq_reader;
+pub mod gibbs;
+pub mod motif_finder;
+pub mod motif_formats;
+pub mod plot;
+pub mod scanner;
+pub mod stats;
+pub mod words;
+
+pub use error::{Error, Result};
@@ -1,29 +1,161 @@
 use clap::Parser;
-use std::path::Path;
+use de_novo_motif_finder::{
+    datastructures::sequence::Sequence,
+    fasta_reader::{self, FastaRecord},
+    gibbs,
+    motif_finder::{self, SearchConfig},
+    motif_formats::{
+        self, counts,
+        homer::{self, HomerMotif},
+        jaspar, meme, transfac, Motif, MotifFormat,
+    },
+    scanner, words, Result,
+};
+use std::{fs::File, io::BufWriter, path::Path, process::ExitCode};
 
 mod args;
-mod datastructures;
-mod fasta_reader;
-mod motif_finder;
-mod plot;
+mod report;
 
-fn main() {
-    let args = args::Args::parse();
-    let path = Path::new(&args.input_file);
+/// P-value the detection thresholds of written HOMER motifs are set to
+const HOMER_P_VALUE: f64 = 1e-4;
 
-    if !path.exists() {
-        panic!("File does not exist: {:?}", path);
+fn main() -> ExitCode {
+    let cli = args::Cli::parse();
+
+    let result = match &cli.command {
+        Some(args::Command::Search(args)) => search(args),
+        Some(args::Command::Scan(args)) => scan(args),
+        None => search(
+            cli.search
+                .as_ref()
+                .expect("clap requires the search arguments"),
+        ),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
     }
+}
+
+fn search(args: &args::Args) -> Result<()> {
+    let mut config = args.search_config()?;
+    let records = fasta_reader::read_sequences(Path::new(&args.input_file), args.parse_options())?;
+
+    let (origins, seqs) = searched_segments(&records, config.min_length);
 
-    let seqs = fasta_reader::read_fasta(path);
+    config.background = args.background(&seqs)?;
 
-    println!("Input file: {:?}", path);
-    println!("Sequences: {}", seqs.len());
-    println!(
-        "Average sequence length: {:.2}",
-        seqs.iter().map(|x| x.len()).sum::<usize>() as f64 / seqs.len() as f64
-    );
-    print!("====================");
+    report::print_input_summary(&args.input_file, &records, &origins, &config.background);
+    println!("Starting search...");
+
+    let controls = match &args.control_file {
+        Some(path) => {
+            let controls = fasta_reader::read_sequences(Path::new(path), args.parse_options())?;
+            Some(searched_segments(&controls, config.min_length).1)
+        }
+        None => None,
+    };
+
+    let gibbs_config = args.gibbs_config();
+    let word_config = args.word_config(controls.clone());
+    let search = |seqs: &[Sequence], config: &SearchConfig| match args.algorithm {
+        args::Algorithm::Beam => motif_finder::motif_finder(seqs, config),
+        args::Algorithm::Gibbs => gibbs::gibbs_motif_finder(seqs, config, &gibbs_config),
+        args::Algorithm::Words => words::word_motif_finder(seqs, config, &word_config),
+    };
+    // The word search already ranks its words against the controls
+    let results = match &controls {
+        Some(controls) if args.algorithm != args::Algorithm::Words => {
+            motif_finder::discriminative_search(&seqs, controls, &config, search)?
+        }
+        _ => search(&seqs, &config)?,
+    };
+    let results = if args.refine {
+        motif_finder::refine_results(results, &seqs, &config, &args.em_config())?
+    } else {
+        results
+    };
+
+    let motifs = results
+        .iter()
+        .map(|result| Motif {
+            name: result.consensus.clone(),
+            pfm: result.pfm.clone(),
+            log_e_value: Some(result.log_e_value),
+        })
+        .collect::<Vec<_>>();
+    if let Some(path) = &args.output_motifs {
+        let writer = BufWriter::new(File::create(path)?);
+        match MotifFormat::from_path(Path::new(path)) {
+            Some(MotifFormat::Meme) => {
+                meme::write_meme(writer, &motifs, &config.background, config.both_strands)?
+            }
+            Some(MotifFormat::Jaspar) => jaspar::write_jaspar(writer, &motifs)?,
+            Some(MotifFormat::JasparPfm) => jaspar::write_jaspar_pfm(writer, &motifs)?,
+            Some(MotifFormat::Transfac) => transfac::write_transfac(writer, &motifs)?,
+            Some(MotifFormat::Homer) => homer::write_homer(
+                writer,
+                &motifs
+                    .iter()
+                    .map(|motif| HomerMotif::with_p_value(motif.clone(), HOMER_P_VALUE))
+                    .collect::<Vec<_>>(),
+            )?,
+            Some(MotifFormat::Counts) | None => counts::write_counts(writer, &motifs)?,
+        }
+    }
+    if let Some(path) = &args.output_meme {
+        meme::write_meme(
+            BufWriter::new(File::create(path)?),
+            &motifs,
+            &config.background,
+            config.both_strands,
+        )?;
+    }
+
+    report::print_results(
+        &results,
+        &records,
+        &origins,
+        &config.background,
+        args.plot_sequence_logos,
+    )
+}
+
+fn scan(args: &args::ScanArgs) -> Result<()> {
+    let motifs = motif_formats::read_motifs(Path::new(&args.motif_file))?;
+    let records = fasta_reader::read_sequences(Path::new(&args.input_file), args.parse_options())?;
+    let (origins, seqs) = searched_segments(&records, 1);
+    let background = args.background()?;
+
+    report::print_scan_header();
+    for motif in motifs.iter() {
+        let hits = scanner::scan(&motif.pfm, &seqs, &background, args.threshold())?;
+        report::print_scan_hits(&motif.name, &hits, motif.pfm.len(), &records, &origins);
+    }
+
+    Ok(())
+}
 
-    motif_finder::motif_finder(seqs, args.plot_sequence_logos, args.hits, args.max_length);
+/// Every unmasked segment that can hold a motif is searched as a sequence of its own. Returns the
+/// record and segment index of each, together with the sequences.
+fn searched_segments(
+    records: &[FastaRecord],
+    min_length: usize,
+) -> (Vec<(usize, usize)>, Vec<Sequence>) {
+    records
+        .iter()
+        .enumerate()
+        .flat_map(|(i, record)| {
+            record
+                .segments
+                .iter()
+                .enumerate()
+                .filter(|(_, segment)| segment.sequence.len() >= min_length)
+                .map(move |(j, segment)| ((i, j), segment.sequence.clone()))
+        })
+        .unzip()
 }
@@ -1,16 +1,129 @@
+//! The motif search.
+
 use std::{
-    collections::{BinaryHeap, HashMap},
-    io::Write,
+    collections::{BinaryHeap, HashMap, HashSet},
+    ops::Range,
+    rc::Rc,
 };
 
 use itertools::Itertools;
+use rand::{rngs::StdRng, SeedableRng};
 
 use crate::{
-    datastructures::{pfm::Pfm, pwm::Pwm, sequence::Sequence},
-    plot,
+    datastructures::{
+        background::Background,
+        pfm::Pfm,
+        pwm::Pwm,
+        sequence::{Sequence, Strand},
+    },
+    em::{self, EmConfig},
+    stats, Error, Result,
 };
 
-struct RankedPfm(Pfm, f64, usize);
+/// How many candidates per requested hit are searched for before ranking them by enrichment
+const CANDIDATES_PER_HIT: usize = 10;
+
+/// Position of a motif occurrence in one of the searched sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Site {
+    /// Index of the sequence in the input given to the search
+    pub sequence: usize,
+    /// Position of the first base of the site on the forward strand, so a site on the reverse
+    /// strand is the reverse complement of the bases from here
+    pub start: usize,
+    pub strand: Strand,
+}
+
+/// A motif found by [`motif_finder`].
+#[derive(Debug, Clone)]
+pub struct MotifResult {
+    pub pfm: Pfm,
+    pub consensus: String,
+    /// Score the motif was ranked by, minus the natural log of its E-value and the skip penalty of
+    /// every sequence left without a site
+    pub score: f64,
+    /// Natural log of the p-value of the log likelihood ratio of the sites
+    pub log_p_value: f64,
+    /// Natural log of the E-value, the p-value times the amount of possible alignments of the
+    /// sequences holding the sites
+    pub log_e_value: f64,
+    /// Information content in bits against the background, corrected for the amount of sites
+    pub information_content: f64,
+    /// The sites the pfm was built from, in the order of the sequences. Unless the model is
+    /// [`SiteModel::Oops`] sequences can be left without a site, and refining with
+    /// [`SiteModel::Tcm`] can give a sequence more than one.
+    pub sites: Vec<Site>,
+    /// Occurrence in the searched versus control sequences, when searched against a control set
+    pub enrichment: Option<Enrichment>,
+    /// Natural log of the likelihood ratio of the sequences under the motif model, when refined by
+    /// [`refine_results`]
+    pub log_likelihood: Option<f64>,
+}
+
+/// How many searched and control sequences have a site of a motif.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Enrichment {
+    pub positives: usize,
+    pub positive_total: usize,
+    pub controls: usize,
+    pub control_total: usize,
+    /// One sided Fisher exact test p-value of the motif being enriched in the searched sequences
+    pub p_value: f64,
+}
+
+impl MotifResult {
+    /// Amount of sequences with at least one site.
+    pub fn sequences_with_site(&self) -> usize {
+        self.sites.iter().map(|site| site.sequence).dedup().count()
+    }
+
+    /// Fraction of the `searched` sequences that have a site.
+    pub fn coverage(&self, searched: usize) -> f64 {
+        self.sequences_with_site() as f64 / searched as f64
+    }
+}
+
+/// How many sites each sequence has. The models apply to the searched sequences, so when records
+/// are split into segments, see [`crate::fasta_reader`], each segment is a sequence of its own and
+/// [`Self::Oops`] asks for a site in every segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SiteModel {
+    /// One occurrence per sequence, every sequence gets a site
+    Oops,
+    /// Zero or one occurrence per sequence, a sequence can be skipped at a penalty
+    Zoops,
+    /// Any number of occurrences per sequence. The search aligns one site per sequence at most
+    /// like [`Self::Zoops`], only [`crate::em`] finds more.
+    Tcm,
+}
+
+/// A pfm together with where each of its aligned sequences starts. While searching, the starts of
+/// sites on the reverse strand are positions in the reverse complement of the sequence.
+#[derive(Debug, Clone, PartialEq)]
+struct Alignment {
+    pfm: Pfm,
+    sites: Vec<Site>,
+}
+
+impl Alignment {
+    fn slice(&self, range: Range<usize>) -> Self {
+        Self {
+            pfm: self.pfm.slice(range.clone()),
+            sites: self
+                .sites
+                .iter()
+                .map(|site| Site {
+                    start: site.start + range.start,
+                    ..*site
+                })
+                .collect(),
+        }
+    }
+}
+
+/// An alignment with its score, the order the sequences after its seed pair are aligned in, and
+/// how many of those it has aligned or skipped.
+struct RankedPfm(Alignment, f64, Rc<[usize]>, usize);
 
 impl PartialEq for RankedPfm {
     fn eq(&self, other: &Self) -> bool {
@@ -32,67 +145,280 @@ impl Ord for RankedPfm {
     }
 }
 
-pub fn motif_finder(seqs: Vec<Sequence>, plot_logos: bool, hits: usize, max_seq_len: usize) {
+/// Which pairs of sequences the search starts from. Every shift of each pair is a seed that is
+/// extended with the other sequences, ordered by how closely they hold the word of up to 8 bases
+/// the pair shares with the most sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Seeding {
+    /// Every pair of sequences, the most thorough and the slowest
+    AllPairs,
+    /// Pairs drawn at random, the same seed draws the same pairs from the same input
+    Sampled { pairs: usize, seed: u64 },
+    /// Pairs holding the words of the min length, up to 8 bases, found in the most sequences, on
+    /// either strand when searching both
+    Similar { pairs: usize },
+}
+
+/// Tunables for the motif search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchConfig {
+    /// Amount of motifs to search for
+    pub hits: usize,
+    /// Min length of the motifs
+    pub min_length: usize,
+    /// Max length of the motifs
+    pub max_length: usize,
+    /// Allowed gap of low scoring columns inside a motif
+    pub max_gap: Option<usize>,
+    /// Log-odds score a column must exceed to be part of a motif
+    pub threshold: f64,
+    /// Amount of best extensions kept each time a sequence is added to a motif
+    pub beam_width: usize,
+    /// Size the priority queue may grow to before it is shrinked
+    pub max_queue_size: usize,
+    /// Size the priority queue is shrinked to
+    pub shrinked_queue_size: usize,
+    /// Also align the reverse complement of each sequence
+    pub both_strands: bool,
+    /// Background model the motifs are scored against
+    pub background: Background,
+    /// Fraction of the way from the lowest to the highest possible Pwm score a window must score
+    /// to count as a site when counting occurrences
+    pub site_threshold: f64,
+    /// Whether every sequence must have a site
+    pub model: SiteModel,
+    /// Natural log units taken off the score of a motif for each sequence it skips, only used by
+    /// [`SiteModel::Zoops`] and [`SiteModel::Tcm`]
+    pub skip_penalty: f64,
+    /// Which pairs of sequences the search starts from
+    pub seeding: Seeding,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            hits: 3,
+            min_length: 6,
+            max_length: 20,
+            max_gap: Some(2),
+            threshold: 0.5,
+            beam_width: 3,
+            max_queue_size: 1_000_000,
+            shrinked_queue_size: 50,
+            both_strands: false,
+            background: Background::uniform(),
+            site_threshold: 0.8,
+            model: SiteModel::Oops,
+            skip_penalty: 1.0,
+            seeding: Seeding::Similar { pairs: 10 },
+        }
+    }
+}
+
+impl SearchConfig {
+    /// Checks that the tunables make sense together.
+    pub fn validate(&self) -> Result<()> {
+        if self.hits == 0 {
+            return Err(Error::InvalidConfig("hits must be at least 1".to_string()));
+        }
+        if self.min_length == 0 {
+            return Err(Error::InvalidConfig(
+                "min length must be at least 1".to_string(),
+            ));
+        }
+        if self.min_length > self.max_length {
+            return Err(Error::InvalidConfig(format!(
+                "min length ({}) can not be larger than max length ({})",
+                self.min_length, self.max_length
+            )));
+        }
+        if !self.threshold.is_finite() {
+            return Err(Error::InvalidConfig(format!(
+                "threshold must be a finite number, got {}",
+                self.threshold
+            )));
+        }
+        if self.beam_width == 0 {
+            return Err(Error::InvalidConfig(
+                "beam width must be at least 1".to_string(),
+            ));
+        }
+        if self.shrinked_queue_size == 0 || self.shrinked_queue_size > self.max_queue_size {
+            return Err(Error::InvalidConfig(format!(
+                "shrinked queue size must be between 1 and max queue size ({}), got {}",
+                self.max_queue_size, self.shrinked_queue_size
+            )));
+        }
+        if !(0.0..=1.0).contains(&self.site_threshold) {
+            return Err(Error::InvalidConfig(format!(
+                "site threshold must be between 0 and 1, got {}",
+                self.site_threshold
+            )));
+        }
+        if !(self.skip_penalty.is_finite() && self.skip_penalty >= 0.0) {
+            return Err(Error::InvalidConfig(format!(
+                "skip penalty must be a non-negative number, got {}",
+                self.skip_penalty
+            )));
+        }
+        if let Seeding::Sampled { pairs: 0, .. } | Seeding::Similar { pairs: 0 } = self.seeding {
+            return Err(Error::InvalidConfig(
+                "at least 1 pair of sequences must be seeded from".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Searches `seqs` for motifs by aligning the sequences one at a time, keeping the best scoring
+/// alignments in a priority queue. The alignments start from the pairs of sequences picked by
+/// `config.seeding`, and are extended with the sequences most like the pair first, see
+/// [`Seeding`]. Unless the model is [`SiteModel::Oops`] each sequence may also be skipped instead
+/// of aligned. Returns at most `config.hits` motifs, best first.
+///
+/// Ties are broken by the bases of the sequences, so unless the seeding is sampled, the same
+/// sequences in another order give the same motifs.
+pub fn motif_finder(seqs: &[Sequence], config: &SearchConfig) -> Result<Vec<MotifResult>> {
+    config.validate()?;
+
+    if seqs.len() < 2 {
+        return Err(Error::EmptyInput(format!(
+            "at least two sequences are needed to search for motifs, got {}",
+            seqs.len()
+        )));
+    }
+
     let mut priority_queue = BinaryHeap::new();
-    let max_priority_queue_size = 1_000_000;
-    let shrinked_priority_queue_size = 50;
 
-    let only_take_top_score = 3;
-    let pfm_min_length = 6;
+    let SearchConfig {
+        hits,
+        min_length: pfm_min_length,
+        max_length: max_seq_len,
+        max_gap,
+        threshold,
+        beam_width: only_take_top_score,
+        max_queue_size: max_priority_queue_size,
+        shrinked_queue_size: shrinked_priority_queue_size,
+        both_strands,
+        ref background,
+        site_threshold: _,
+        model,
+        skip_penalty: _,
+        seeding: _,
+    } = *config;
+
+    let reverse_seqs = if both_strands {
+        seqs.iter().map(|seq| seq.reverse_complement()).collect()
+    } else {
+        Vec::new()
+    };
+
+    for SeedPair {
+        first,
+        second,
+        order,
+    } in seed_pairs(seqs, config)
+    {
+        let to_pair = |mut alignment: Alignment| {
+            for site in alignment.sites.iter_mut() {
+                site.sequence = if site.sequence == 0 { first } else { second };
+            }
+            alignment
+        };
 
-    let threshold = 0.5;
-    let max_gap = Some(2);
+        // Seeds where the second sequence is flipped are enough to get every relative orientation
+        let mut seeds = get_all_shift_pfms(&seqs[first], &seqs[second], pfm_min_length)
+            .into_iter()
+            .map(to_pair)
+            .collect::<Vec<_>>();
+        if both_strands {
+            seeds.extend(
+                get_all_shift_pfms(&seqs[first], &reverse_seqs[second], pfm_min_length)
+                    .into_iter()
+                    .map(|mut alignment| {
+                        alignment.sites[1].strand = Strand::Reverse;
+                        to_pair(alignment)
+                    }),
+            );
+        }
 
-    get_all_shift_pfms(&seqs[0], &seqs[1], pfm_min_length)
-        .into_iter()
-        .flat_map(|x| {
-            extraxt_high_interest_area(x, threshold, pfm_min_length, max_seq_len, max_gap)
-        })
-        .for_each(|pfm| {
-            let score = pfm.clone().get_custom_score();
-            priority_queue.push(RankedPfm(pfm, score, 2));
-        });
+        seeds
+            .into_iter()
+            .flat_map(|x| {
+                extraxt_high_interest_area(
+                    x,
+                    threshold,
+                    pfm_min_length,
+                    max_seq_len,
+                    max_gap,
+                    background,
+                )
+            })
+            .for_each(|alignment| {
+                let score = rank(&alignment, 2, seqs, &reverse_seqs, config);
+                priority_queue.push(RankedPfm(alignment, score, order.clone(), 0));
+            });
+    }
 
-    println!("Starting search...");
     let mut top_results = HashMap::new();
+    let mut expanded = HashSet::new();
 
-    while let Some(RankedPfm(pfm, score, indicies)) = priority_queue.pop() {
-        if top_results.contains_key(&pfm.get_consensus_string()) {
+    while let Some(RankedPfm(alignment, score, order, extended)) = priority_queue.pop() {
+        let key = motif_key(&alignment.pfm, both_strands);
+        if top_results.contains_key(&key) {
+            continue;
+        }
+        // Only the best alignment of a motif is extended at each depth, or the search keeps
+        // coming back to alignments of it that lose score as they grow
+        if !expanded.insert((key.clone(), extended)) {
             continue;
         }
-        print!(
-            "\rScore: {:.2}, Depth: {}, Size: {}, String: {:?}",
-            score,
-            indicies,
-            priority_queue.len(),
-            pfm.get_consensus_string()
-        );
-
-        std::io::stdout().flush().unwrap();
 
-        if indicies >= seqs.len() {
-            top_results
-                .entry(pfm.get_consensus_string())
-                .or_insert((pfm, score));
+        let Some(&next) = order.get(extended) else {
+            top_results.entry(key).or_insert((alignment, score));
 
             if top_results.len() >= hits {
-                println!("Found all hits!");
                 break;
             }
 
             continue;
+        };
+        // The seed pair and every sequence up to and including the next one
+        let aligned = extended + 3;
+
+        let mut extensions = get_all_shift_pfms_with_pfm(
+            &alignment,
+            &seqs[next],
+            next,
+            Strand::Forward,
+            pfm_min_length,
+        );
+        if both_strands {
+            extensions.extend(get_all_shift_pfms_with_pfm(
+                &alignment,
+                &reverse_seqs[next],
+                next,
+                Strand::Reverse,
+                pfm_min_length,
+            ));
         }
 
-        let next_seq = &seqs[indicies];
-
-        get_all_shift_pfms_with_pfm(&pfm, next_seq, pfm_min_length)
+        extensions
             .into_iter()
             .flat_map(|x| {
-                extraxt_high_interest_area(x, threshold, pfm_min_length, max_seq_len, max_gap)
+                extraxt_high_interest_area(
+                    x,
+                    threshold,
+                    pfm_min_length,
+                    max_seq_len,
+                    max_gap,
+                    background,
+                )
             })
-            .map(|pfm| {
-                let new_score = pfm.get_custom_score();
-                RankedPfm(pfm, new_score, indicies + 1)
+            .map(|alignment| {
+                let score = rank(&alignment, aligned, seqs, &reverse_seqs, config);
+                RankedPfm(alignment, score, order.clone(), extended + 1)
             })
             .sorted_by(|a, b| b.1.partial_cmp(&a.1).unwrap())
             .take(only_take_top_score)
@@ -100,6 +426,11 @@ pub fn motif_finder(seqs: Vec<Sequence>, plot_logos: bool, hits: usize, max_seq_
                 priority_queue.push(x);
             });
 
+        if model != SiteModel::Oops {
+            let score = rank(&alignment, aligned, seqs, &reverse_seqs, config);
+            priority_queue.push(RankedPfm(alignment, score, order, extended + 1));
+        }
+
         if priority_queue.len() > max_priority_queue_size {
             let mut new_priority_queue = BinaryHeap::new();
             for _ in 0..shrinked_priority_queue_size {
@@ -109,30 +440,533 @@ pub fn motif_finder(seqs: Vec<Sequence>, plot_logos: bool, hits: usize, max_seq_
         }
     }
 
-    for (consensus_string, (pfm, score)) in top_results
+    Ok(top_results
         .into_iter()
-        .sorted_by(|a, b| b.1 .1.partial_cmp(&a.1 .1).unwrap())
-        .take(3)
-    {
-        //println!("Score: {:.2}", score);
-        println!("{:?}", consensus_string);
-        println!(
-            "Kullback-Leibler divergence: {:.2}",
-            pfm.kullback_leibler_divergence()
-        );
-        // println!("{:?}", pfm.matrix);
+        .sorted_by(|a, b| b.1 .1.partial_cmp(&a.1 .1).unwrap().then(a.0.cmp(&b.0)))
+        .take(hits)
+        .map(|(_, (alignment, score))| {
+            let width = alignment.pfm.len();
+            let (log_p_value, log_e_value) = significance(&alignment, seqs, &reverse_seqs, config);
+            let sites = alignment
+                .sites
+                .into_iter()
+                .map(|site| match site.strand {
+                    Strand::Forward => site,
+                    Strand::Reverse => Site {
+                        start: seqs[site.sequence].len() - site.start - width,
+                        ..site
+                    },
+                })
+                .sorted_by_key(|site| site.sequence)
+                .collect();
+
+            MotifResult {
+                information_content: alignment.pfm.information_content(background),
+                consensus: alignment.pfm.get_consensus_string(),
+                pfm: alignment.pfm,
+                score,
+                log_p_value,
+                log_e_value,
+                sites,
+                enrichment: None,
+                log_likelihood: None,
+            }
+        })
+        .collect())
+}
+
+/// Refines each of `results` on `seqs` by expectation maximization with [`em::refine`], replacing
+/// its pfm and sites by the refined ones and scoring it again. The results keep their order and
+/// enrichment, and results the refinement leaves without sites are kept as they were.
+pub fn refine_results(
+    results: Vec<MotifResult>,
+    seqs: &[Sequence],
+    config: &SearchConfig,
+    em_config: &EmConfig,
+) -> Result<Vec<MotifResult>> {
+    let reverse_seqs = if config.both_strands {
+        seqs.iter().map(|seq| seq.reverse_complement()).collect()
+    } else {
+        Vec::new()
+    };
+
+    results
+        .into_iter()
+        .map(|result| {
+            let refinement = em::refine(
+                &result.pfm,
+                seqs,
+                &config.background,
+                config.both_strands,
+                em_config,
+            )?;
+            if refinement.sites.is_empty() {
+                return Ok(result);
+            }
+
+            Ok(MotifResult {
+                log_likelihood: Some(refinement.log_likelihood),
+                enrichment: result.enrichment,
+                ..result_from_sites(
+                    refinement.sites,
+                    result.pfm.len(),
+                    seqs,
+                    &reverse_seqs,
+                    config,
+                )
+            })
+        })
+        .collect()
+}
+
+/// The result of the motif of `width` made of `sites`, which are given as in
+/// [`MotifResult::sites`], scored like the results of [`motif_finder`]. `reverse_seqs` are the
+/// reverse complements of `seqs` when searching both strands.
+pub(crate) fn result_from_sites(
+    sites: Vec<Site>,
+    width: usize,
+    seqs: &[Sequence],
+    reverse_seqs: &[Sequence],
+    config: &SearchConfig,
+) -> MotifResult {
+    let matched = sites
+        .iter()
+        .map(|site| {
+            let matched = seqs[site.sequence].slice(site.start..site.start + width);
+            match site.strand {
+                Strand::Forward => matched,
+                Strand::Reverse => matched.reverse_complement(),
+            }
+        })
+        .collect::<Vec<_>>();
+    // Sites on the reverse strand start in the reverse complement while scoring
+    let alignment = Alignment {
+        pfm: Pfm::from_sequences(&matched),
+        sites: sites
+            .iter()
+            .map(|&site| match site.strand {
+                Strand::Forward => site,
+                Strand::Reverse => Site {
+                    start: seqs[site.sequence].len() - site.start - width,
+                    ..site
+                },
+            })
+            .collect(),
+    };
+    let (log_p_value, log_e_value) = significance(&alignment, seqs, reverse_seqs, config);
+
+    MotifResult {
+        score: rank(&alignment, seqs.len(), seqs, reverse_seqs, config),
+        information_content: alignment.pfm.information_content(&config.background),
+        consensus: alignment.pfm.get_consensus_string(),
+        pfm: alignment.pfm,
+        log_p_value,
+        log_e_value,
+        sites,
+        enrichment: None,
+        log_likelihood: None,
+    }
+}
+
+/// Searches `seqs` for motifs like [`motif_finder`], but ranks them by how enriched their sites
+/// are in `seqs` compared to `controls`, most significant first.
+pub fn discriminative_motif_finder(
+    seqs: &[Sequence],
+    controls: &[Sequence],
+    config: &SearchConfig,
+) -> Result<Vec<MotifResult>> {
+    discriminative_search(seqs, controls, config, motif_finder)
+}
+
+/// Ranks the candidates `search` finds in `seqs` by enrichment like
+/// [`discriminative_motif_finder`], for searches other than [`motif_finder`].
+pub fn discriminative_search(
+    seqs: &[Sequence],
+    controls: &[Sequence],
+    config: &SearchConfig,
+    search: impl Fn(&[Sequence], &SearchConfig) -> Result<Vec<MotifResult>>,
+) -> Result<Vec<MotifResult>> {
+    if controls.is_empty() {
+        return Err(Error::EmptyInput(
+            "no control sequences to compare against".to_string(),
+        ));
+    }
+
+    let candidates = search(
+        seqs,
+        &SearchConfig {
+            hits: config.hits * CANDIDATES_PER_HIT,
+            ..config.clone()
+        },
+    )?;
+
+    Ok(candidates
+        .into_iter()
+        .map(|mut result| {
+            let positives = count_with_site(&result.pfm, seqs, config);
+            let controls_with_site = count_with_site(&result.pfm, controls, config);
+
+            result.enrichment = Some(Enrichment {
+                positives,
+                positive_total: seqs.len(),
+                controls: controls_with_site,
+                control_total: controls.len(),
+                p_value: stats::fisher_enrichment(
+                    positives,
+                    seqs.len(),
+                    controls_with_site,
+                    controls.len(),
+                ),
+            });
+            result
+        })
+        .sorted_by(|a, b| {
+            let p_value = |result: &MotifResult| result.enrichment.map_or(1.0, |e| e.p_value);
+            p_value(a)
+                .partial_cmp(&p_value(b))
+                .unwrap()
+                .then(b.score.partial_cmp(&a.score).unwrap())
+        })
+        .take(config.hits)
+        .collect())
+}
+
+/// Longest words used to pick seed pairs and order the other sequences
+const MAX_SEED_WORD: usize = 8;
+
+/// A pair of sequences to seed from, with the other sequences in the order they are aligned in.
+struct SeedPair {
+    first: usize,
+    second: usize,
+    order: Rc<[usize]>,
+}
+
+/// The words of up to [`MAX_SEED_WORD`] bases in the sequences, with each word and its reverse
+/// complement counted as the same when searching both strands.
+struct SeedWords {
+    length: usize,
+    both_strands: bool,
+    /// The distinct words of each sequence, sorted
+    words: Vec<Vec<u64>>,
+    /// Amount of sequences holding each word
+    counts: HashMap<u64, usize>,
+    /// Position of each sequence when sorted by its bases, used to break ties
+    ranks: Vec<usize>,
+}
+
+impl SeedWords {
+    fn new(seqs: &[Sequence], config: &SearchConfig) -> Self {
+        let length = config.min_length.min(MAX_SEED_WORD);
+        let words = seqs
+            .iter()
+            .map(|seq| {
+                window_codes(seq, length)
+                    .map(|code| {
+                        if config.both_strands {
+                            code.min(reverse_complement_code(code, length))
+                        } else {
+                            code
+                        }
+                    })
+                    .sorted()
+                    .dedup()
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut counts = HashMap::new();
+        for &code in words.iter().flatten() {
+            *counts.entry(code).or_insert(0) += 1;
+        }
+
+        let mut ranks = vec![0; seqs.len()];
+        for (rank, i) in (0..seqs.len()).sorted_by_key(|&i| &seqs[i]).enumerate() {
+            ranks[i] = rank;
+        }
+
+        Self {
+            length,
+            both_strands: config.both_strands,
+            words,
+            counts,
+            ranks,
+        }
+    }
+
+    /// The word `a` and `b` share that the most sequences hold, the smallest on ties.
+    fn shared_word(&self, a: usize, b: usize) -> Option<u64> {
+        let b_words = &self.words[b];
+        self.words[a]
+            .iter()
+            .filter(|code| b_words.binary_search(code).is_ok())
+            .max_by_key(|&code| (self.counts[code], std::cmp::Reverse(*code)))
+            .copied()
+    }
+
+    /// The sequences by how few mismatches their best window has to `word` on a searched strand,
+    /// then by their bases. Without a word they are only sorted by their bases.
+    fn order(&self, seqs: &[Sequence], word: Option<u64>) -> Vec<usize> {
+        let Some(word) = word else {
+            return (0..seqs.len()).sorted_by_key(|&i| self.ranks[i]).collect();
+        };
+        let mut words = vec![word];
+        if self.both_strands {
+            words.push(reverse_complement_code(word, self.length));
+        }
+
+        (0..seqs.len())
+            .map(|i| {
+                let mismatches = window_codes(&seqs[i], self.length)
+                    .flat_map(|code| words.iter().map(move |&word| mismatches(code, word)))
+                    .min()
+                    .unwrap_or(u32::MAX);
+                (mismatches, self.ranks[i], i)
+            })
+            .sorted()
+            .map(|(_, _, i)| i)
+            .collect()
+    }
+}
 
-        if plot_logos {
-            let pwm = Pwm::pfm_into_ppm(pfm);
-            plot::plot_pwm(&format!("{}.png", consensus_string), &pwm, score).unwrap();
+/// The pairs of sequences picked by `config.seeding`, each ordered by their bases. Each pair
+/// aligns the other sequences by how closely they hold the most common word the pair shares.
+fn seed_pairs(seqs: &[Sequence], config: &SearchConfig) -> Vec<SeedPair> {
+    let words = SeedWords::new(seqs, config);
+    let ordered = |a: usize, b: usize| {
+        if words.ranks[b] < words.ranks[a] {
+            (b, a)
+        } else {
+            (a, b)
         }
-        println!();
+    };
+
+    let pairs = match config.seeding {
+        Seeding::AllPairs => (0..seqs.len())
+            .tuple_combinations()
+            .map(|(a, b)| ordered(a, b))
+            .sorted_by_key(|&(a, b)| (words.ranks[a], words.ranks[b]))
+            .map(|(a, b)| (a, b, words.shared_word(a, b)))
+            .collect::<Vec<_>>(),
+        Seeding::Sampled { pairs, seed } => {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let amount = seqs.len() * (seqs.len() - 1) / 2;
+            rand::seq::index::sample(&mut rng, amount, pairs.min(amount))
+                .into_iter()
+                .map(|i| {
+                    let (a, b) = pair_from_index(i, seqs.len());
+                    let (a, b) = ordered(a, b);
+                    (a, b, words.shared_word(a, b))
+                })
+                .collect()
+        }
+        Seeding::Similar { pairs } => {
+            // The two sequences that sort first of those holding each word
+            let mut holders = HashMap::<u64, Vec<usize>>::new();
+            for i in (0..seqs.len()).sorted_by_key(|&i| words.ranks[i]) {
+                for &code in words.words[i].iter() {
+                    let holders = holders.entry(code).or_default();
+                    if holders.len() < 2 {
+                        holders.push(i);
+                    }
+                }
+            }
+
+            let mut seen = HashSet::new();
+            let similar = words
+                .counts
+                .iter()
+                .filter(|(_, &count)| count >= 2)
+                .sorted_by_key(|(&code, &count)| (std::cmp::Reverse(count), code))
+                .map(|(code, _)| (holders[code][0], holders[code][1], Some(*code)))
+                .filter(|&(a, b, _)| seen.insert((a, b)))
+                .take(pairs)
+                .collect::<Vec<_>>();
+
+            if similar.is_empty() {
+                // No sequences share a word, so any pair is as good as another
+                let mut by_bases = (0..seqs.len()).sorted_by_key(|&i| words.ranks[i]);
+                vec![(by_bases.next().unwrap(), by_bases.next().unwrap(), None)]
+            } else {
+                similar
+            }
+        }
+    };
+
+    let mut orders = HashMap::new();
+    pairs
+        .into_iter()
+        .map(|(first, second, word)| {
+            let order = orders
+                .entry(word)
+                .or_insert_with(|| words.order(seqs, word));
+            SeedPair {
+                first,
+                second,
+                order: order
+                    .iter()
+                    .copied()
+                    .filter(|&i| i != first && i != second)
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// The pair of indices in `0..n` at `index` in the order of [`Itertools::tuple_combinations`].
+fn pair_from_index(index: usize, n: usize) -> (usize, usize) {
+    // Pairs starting at a come after the n - 1 + n - 2 + ... + n - a pairs before them
+    let before = |a: usize| a * (2 * n - a - 1) / 2;
+    let discriminant = ((2 * n - 1) * (2 * n - 1) - 8 * index) as f64;
+    let mut a = ((2 * n - 1) as f64 - discriminant.sqrt()) as usize / 2;
+    // The float estimate can be off by one either way
+    while a > 0 && before(a) > index {
+        a -= 1;
+    }
+    while before(a + 1) <= index {
+        a += 1;
+    }
+
+    (a, a + 1 + index - before(a))
+}
+
+/// Codes of every window of `length` bases in `seq`, two bits per base.
+fn window_codes(seq: &Sequence, length: usize) -> impl Iterator<Item = u64> + '_ {
+    let mask = (1u64 << (2 * length)) - 1;
+    seq.bases
+        .iter()
+        .scan(0u64, move |code, base| {
+            *code = (*code << 2 | base.to_index() as u64) & mask;
+            Some(*code)
+        })
+        .skip(length.saturating_sub(1))
+        .take((seq.len() + 1).saturating_sub(length))
+}
+
+fn reverse_complement_code(code: u64, length: usize) -> u64 {
+    (0..length).fold(0, |reverse, i| reverse << 2 | (3 - (code >> (2 * i) & 3)))
+}
+
+/// Amount of bases that differ between the words of two codes.
+fn mismatches(a: u64, b: u64) -> u32 {
+    let differing = a ^ b;
+    ((differing | differing >> 1) & 0x5555_5555_5555_5555).count_ones()
+}
+
+/// Amount of `seqs` with a window scoring above the site threshold on either searched strand.
+/// The reverse strand is scored with the matrix of the reverse complement of `pfm`, against the
+/// background of the bases as they are read on the forward strand.
+fn count_with_site(pfm: &Pfm, seqs: &[Sequence], config: &SearchConfig) -> usize {
+    let mut pfms = vec![pfm.clone()];
+    if config.both_strands {
+        pfms.push(pfm.reverse_complement());
     }
+    let strands = pfms
+        .iter()
+        .map(|pfm| {
+            let pwm = Pwm::log_odds(pfm, &config.background);
+            let (min, max) = (pwm.min_score(), pwm.max_score());
+            (pwm, min + config.site_threshold * (max - min))
+        })
+        .collect::<Vec<_>>();
 
-    plot::clear_cache();
+    seqs.iter()
+        .filter(|seq| {
+            strands.iter().any(|(pwm, cutoff)| {
+                (0..(seq.len() + 1).saturating_sub(pwm.len()))
+                    .any(|start| pwm.score_window(seq, start, &config.background) >= *cutoff)
+            })
+        })
+        .count()
 }
 
-fn get_all_shift_pfms_with_pfm<'a>(pfm: &'a Pfm, seq: &'a Sequence, min_len: usize) -> Vec<Pfm> {
+/// Score an alignment of the first `aligned` sequences is ranked by, minus the natural log of its
+/// E-value and the skip penalty of each of those sequences without a site.
+fn rank(
+    alignment: &Alignment,
+    aligned: usize,
+    seqs: &[Sequence],
+    reverse_seqs: &[Sequence],
+    config: &SearchConfig,
+) -> f64 {
+    let (_, log_e_value) = significance(alignment, seqs, reverse_seqs, config);
+    let with_site = alignment
+        .sites
+        .iter()
+        .map(|site| site.sequence)
+        .dedup()
+        .count();
+    let skipped = aligned.saturating_sub(with_site);
+
+    -log_e_value - config.skip_penalty * skipped as f64
+}
+
+/// Natural logs of the p-value and E-value of an alignment. The E-value is the p-value times the
+/// amount of alignments that could have been picked from the sequences holding its sites. With a
+/// Markov background the base composition part of the log likelihood ratio is swapped for the
+/// likelihood of each site in its context.
+fn significance(
+    alignment: &Alignment,
+    seqs: &[Sequence],
+    reverse_seqs: &[Sequence],
+    config: &SearchConfig,
+) -> (f64, f64) {
+    let background = &config.background;
+    let width = alignment.pfm.len();
+
+    let mut log_likelihood_ratio = alignment.pfm.log_likelihood_ratio(background);
+    if background.order() > 0 {
+        let composition = alignment
+            .pfm
+            .matrix
+            .iter()
+            .flat_map(|row| row.iter().zip(background.frequencies))
+            .map(|(&count, q)| count as f64 * q.log2())
+            .sum::<f64>();
+        let markov = alignment
+            .sites
+            .iter()
+            .map(|site| {
+                let seq = match site.strand {
+                    Strand::Forward => &seqs[site.sequence],
+                    Strand::Reverse => &reverse_seqs[site.sequence],
+                };
+                background.window_log_likelihood(seq, site.start, width)
+            })
+            .sum::<f64>();
+
+        log_likelihood_ratio += (composition - markov) * std::f64::consts::LN_2;
+    }
+
+    let strands = if config.both_strands { 2.0 } else { 1.0 };
+    let log_alignments = alignment
+        .sites
+        .iter()
+        .map(|site| (strands * (seqs[site.sequence].len() + 1 - width) as f64).ln())
+        .sum::<f64>();
+
+    let log_p_value = alignment.pfm.log_p_value(log_likelihood_ratio);
+    (log_p_value, log_p_value + log_alignments)
+}
+
+/// Motifs with the same key are the same motif. When searching both strands a motif and its
+/// reverse complement are the same.
+pub(crate) fn motif_key(pfm: &Pfm, both_strands: bool) -> String {
+    let consensus = pfm.get_consensus_string();
+    if !both_strands {
+        return consensus;
+    }
+
+    consensus.min(pfm.reverse_complement().get_consensus_string())
+}
+
+fn get_all_shift_pfms_with_pfm(
+    alignment: &Alignment,
+    seq: &Sequence,
+    seq_index: usize,
+    strand: Strand,
+    min_len: usize,
+) -> Vec<Alignment> {
+    let pfm = &alignment.pfm;
     let mut pwms = Vec::new();
     let min_len = min_len as i32;
 
@@ -152,9 +986,14 @@ fn get_all_shift_pfms_with_pfm<'a>(pfm: &'a Pfm, seq: &'a Sequence, min_len: usi
         let seq_from = (pwm_shift).max(0) as usize;
         let seq_to = (pwm_shift + pwm_to as i32) as usize;
 
-        let mut pwm_clone = pfm.slice(pwm_from..pwm_to);
+        let mut pwm_clone = alignment.slice(pwm_from..pwm_to);
 
-        pwm_clone.add_sequence(&seq.slice(seq_from..seq_to));
+        pwm_clone.pfm.add_sequence(&seq.slice(seq_from..seq_to));
+        pwm_clone.sites.push(Site {
+            sequence: seq_index,
+            start: seq_from,
+            strand,
+        });
 
         pwms.push(pwm_clone);
 
@@ -164,15 +1003,18 @@ fn get_all_shift_pfms_with_pfm<'a>(pfm: &'a Pfm, seq: &'a Sequence, min_len: usi
     pwms
 }
 
+/// The sites of the returned alignments refer to `seq_1` as sequence 0 and `seq_2` as sequence 1,
+/// both on the forward strand.
 fn get_all_shift_pfms<'a>(
     mut seq_1: &'a Sequence,
     mut seq_2: &'a Sequence,
     min_len: usize,
-) -> Vec<Pfm> {
+) -> Vec<Alignment> {
     let mut pfms = Vec::new();
     let min_len = min_len as i32;
 
-    if seq_1.len() > seq_2.len() {
+    let swapped = seq_1.len() > seq_2.len();
+    if swapped {
         std::mem::swap(&mut seq_1, &mut seq_2);
     }
 
@@ -207,10 +1049,28 @@ fn get_all_shift_pfms<'a>(
             seq_2_to
         );
 
-        pfms.push(Pfm::from_sequences(&[
-            seq_1.slice(seq_1_from..seq_1_to),
-            seq_2.slice(seq_2_from..seq_2_to),
-        ]));
+        let (seq_1_index, seq_2_index) = if swapped { (1, 0) } else { (0, 1) };
+        let mut sites = vec![
+            Site {
+                sequence: seq_1_index,
+                start: seq_1_from,
+                strand: Strand::Forward,
+            },
+            Site {
+                sequence: seq_2_index,
+                start: seq_2_from,
+                strand: Strand::Forward,
+            },
+        ];
+        sites.sort_by_key(|site| site.sequence);
+
+        pfms.push(Alignment {
+            pfm: Pfm::from_sequences(&[
+                seq_1.slice(seq_1_from..seq_1_to),
+                seq_2.slice(seq_2_from..seq_2_to),
+            ]),
+            sites,
+        });
 
         seq_1_shift += 1;
     }
@@ -219,17 +1079,18 @@ fn get_all_shift_pfms<'a>(
 }
 
 fn extraxt_high_interest_area(
-    pfm: Pfm,
+    alignment: Alignment,
     threshold: f64,
     min_len: usize,
     max_len: usize,
     max_gap: Option<usize>,
-) -> Vec<Pfm> {
-    let mut pfm_copy = pfm.clone();
+    background: &Background,
+) -> Vec<Alignment> {
+    let mut pfm_copy = alignment.pfm.clone();
     pfm_copy.additive_smoothing(1);
 
     // dbg!(&pfm_copy);
-    let pwm = Pwm::from(pfm_copy);
+    let pwm = Pwm::log_odds(&pfm_copy, background);
 
     let mut high_interest_areas = Vec::new();
     // Find the high interest areas
@@ -283,14 +1144,14 @@ fn extraxt_high_interest_area(
                     continue;
                 }
 
-                high_interest_areas.push(pfm.slice(start_point..p));
+                high_interest_areas.push(alignment.slice(start_point..p));
 
                 if let Some(max_gap) = max_gap {
                     let saturated_padded_end_point = (p + max_gap).min(pwm.matrix.len() - 1);
 
                     if saturated_padded_end_point != p {
                         high_interest_areas
-                            .push(pfm.slice(start_point..saturated_padded_end_point));
+                            .push(alignment.slice(start_point..saturated_padded_end_point));
                     }
                 }
             }
@@ -307,13 +1168,14 @@ fn extraxt_high_interest_area(
                 continue;
             }
 
-            high_interest_areas.push(pfm.slice(start_point..p));
+            high_interest_areas.push(alignment.slice(start_point..p));
 
             if let Some(max_gap) = max_gap {
                 let saturated_padded_end_point = (p + max_gap).min(pwm.matrix.len() - 1);
 
                 if saturated_padded_end_point != p {
-                    high_interest_areas.push(pfm.slice(start_point..saturated_padded_end_point));
+                    high_interest_areas
+                        .push(alignment.slice(start_point..saturated_padded_end_point));
                 }
             }
         }
@@ -326,6 +1188,25 @@ fn extraxt_high_interest_area(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_search_config_validate() {
+        assert!(SearchConfig::default().validate().is_ok());
+
+        let config = SearchConfig {
+            min_length: 8,
+            max_length: 6,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = SearchConfig {
+            shrinked_queue_size: 100,
+            max_queue_size: 10,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
     #[test]
     fn test_get_all_shift_pfms() {
         let seq_1 = Sequence::from("ACGT");
@@ -335,47 +1216,309 @@ mod tests {
         let res = get_all_shift_pfms(&seq_1, &seq_2, min_len);
 
         assert_eq!(
-            res[0],
+            res[0].pfm,
             Pfm::from_sequences(&[Sequence::from("GT"), Sequence::from("AC")])
         );
         assert_eq!(
-            res[1],
+            res[1].pfm,
             Pfm::from_sequences(&[Sequence::from("CGT"), Sequence::from("ACG")])
         );
         assert_eq!(
-            res[2],
+            res[2].pfm,
             Pfm::from_sequences(&[Sequence::from("ACGT"), Sequence::from("ACGT")])
         );
         assert_eq!(
-            res[3],
+            res[3].pfm,
             Pfm::from_sequences(&[Sequence::from("ACGT"), Sequence::from("CGTA")])
         );
         assert_eq!(
-            res[4],
+            res[4].pfm,
             Pfm::from_sequences(&[Sequence::from("ACG"), Sequence::from("GTA")])
         );
         assert_eq!(
-            res[5],
+            res[5].pfm,
             Pfm::from_sequences(&[Sequence::from("AC"), Sequence::from("TA")])
         );
     }
 
+    #[test]
+    fn test_get_all_shift_pfms_sites() {
+        let seq_1 = Sequence::from("ACGTA");
+        let seq_2 = Sequence::from("ACGT");
+
+        let res = get_all_shift_pfms(&seq_1, &seq_2, 2);
+
+        assert_eq!(
+            res[0].sites,
+            vec![
+                Site {
+                    sequence: 0,
+                    start: 0,
+                    strand: Strand::Forward
+                },
+                Site {
+                    sequence: 1,
+                    start: 2,
+                    strand: Strand::Forward
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_motif_finder_sites_match_pfm() {
+        let seqs = [
+            Sequence::from("GGTATAACCGGTTCA"),
+            Sequence::from("CAACCGGTTTGCA"),
+            Sequence::from("TTTGAACCGGTTG"),
+        ];
+        let config = SearchConfig {
+            hits: 1,
+            min_length: 4,
+            ..Default::default()
+        };
+
+        let results = motif_finder(&seqs, &config).unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].sites.len(), seqs.len());
+
+        let matched = results[0]
+            .sites
+            .iter()
+            .map(|site| seqs[site.sequence].slice(site.start..site.start + results[0].pfm.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(Pfm::from_sequences(&matched), results[0].pfm);
+    }
+
+    #[test]
+    fn test_motif_finder_both_strands() {
+        let seqs = [
+            Sequence::from("CCTGATTACAGTTA").reverse_complement(),
+            Sequence::from("AGCGATTACAGCTC"),
+            Sequence::from("TTGATTACAGGA").reverse_complement(),
+        ];
+        let config = SearchConfig {
+            hits: 1,
+            min_length: 6,
+            both_strands: true,
+            ..Default::default()
+        };
+
+        let results = motif_finder(&seqs, &config).unwrap();
+
+        let width = results[0].pfm.len();
+        let matched = results[0]
+            .sites
+            .iter()
+            .map(|site| {
+                let matched = seqs[site.sequence].slice(site.start..site.start + width);
+                match site.strand {
+                    Strand::Forward => matched,
+                    Strand::Reverse => matched.reverse_complement(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(Pfm::from_sequences(&matched), results[0].pfm);
+        assert_ne!(results[0].sites[0].strand, results[0].sites[1].strand);
+        assert_eq!(results[0].sites[0].strand, results[0].sites[2].strand);
+    }
+
+    #[test]
+    fn test_motif_finder_ranks_by_e_value() {
+        let seqs = [
+            Sequence::from("GGTATAACCGGTTCA"),
+            Sequence::from("CAACCGGTTTGCA"),
+            Sequence::from("TTTGAACCGGTTG"),
+        ];
+        let config = SearchConfig {
+            hits: 3,
+            min_length: 4,
+            ..Default::default()
+        };
+
+        let results = motif_finder(&seqs, &config).unwrap();
+
+        assert!(results[0].consensus.contains("ACCGGTT"), "{:?}", results);
+        for result in results.iter() {
+            assert_eq!(result.score, -result.log_e_value);
+            assert!(result.log_p_value < result.log_e_value);
+        }
+        assert!(results
+            .windows(2)
+            .all(|pair| pair[0].log_e_value <= pair[1].log_e_value));
+    }
+
+    #[test]
+    fn test_motif_finder_zoops_skips_sequence() {
+        let seqs = [
+            Sequence::from("GGTATAACCGGTTCA"),
+            Sequence::from("CAACCGGTTTGCA"),
+            Sequence::from("GCGCGCGCGCGCG"),
+            Sequence::from("TTTGAACCGGTTG"),
+        ];
+        let config = SearchConfig {
+            hits: 1,
+            min_length: 4,
+            model: SiteModel::Zoops,
+            ..Default::default()
+        };
+
+        let results = motif_finder(&seqs, &config).unwrap();
+
+        assert!(results[0].consensus.contains("ACCGGTT"), "{:?}", results);
+        assert_eq!(
+            results[0]
+                .sites
+                .iter()
+                .map(|site| site.sequence)
+                .collect::<Vec<_>>(),
+            vec![0, 1, 3]
+        );
+        assert_eq!(results[0].coverage(seqs.len()), 0.75);
+    }
+
+    #[test]
+    fn test_motif_finder_independent_of_input_order() {
+        // The motif is missing from the first two sequences
+        let seqs = [
+            Sequence::from("GCGCGCGCGCGCG"),
+            Sequence::from("ATATATATATATAT"),
+            Sequence::from("GGTATAACCGGTTCA"),
+            Sequence::from("CAACCGGTTTGCA"),
+            Sequence::from("TTTGAACCGGTTG"),
+            Sequence::from("CTAACCGGTTAGG"),
+        ];
+        let config = SearchConfig {
+            hits: 1,
+            min_length: 4,
+            model: SiteModel::Zoops,
+            ..Default::default()
+        };
+        let found = |seqs: &[Sequence]| {
+            let result = motif_finder(seqs, &config).unwrap().remove(0);
+            let sites = result
+                .sites
+                .iter()
+                .map(|site| seqs[site.sequence].clone())
+                .sorted()
+                .collect::<Vec<_>>();
+            (result.consensus, sites)
+        };
+
+        let (consensus, sites) = found(&seqs);
+        assert!(consensus.contains("ACCGGTT"), "{}", consensus);
+        assert_eq!(
+            sites,
+            seqs[2..].iter().cloned().sorted().collect::<Vec<_>>()
+        );
+
+        for order in [[5, 4, 3, 2, 1, 0], [3, 0, 5, 1, 4, 2], [2, 3, 4, 5, 0, 1]] {
+            let shuffled = order.map(|i| seqs[i].clone());
+            assert_eq!(found(&shuffled), (consensus.clone(), sites.clone()));
+        }
+    }
+
+    #[test]
+    fn test_pair_from_index() {
+        for n in [2, 3, 7, 50] {
+            assert_eq!(
+                (0..n * (n - 1) / 2)
+                    .map(|i| pair_from_index(i, n))
+                    .collect::<Vec<_>>(),
+                (0..n).tuple_combinations().collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_refine_results_sites_match_pfm() {
+        let seqs = [
+            Sequence::from("CCTGATTACAGTTA").reverse_complement(),
+            Sequence::from("AGCGATTACAGCTC"),
+            Sequence::from("TTGATTACAGGA").reverse_complement(),
+        ];
+        let config = SearchConfig {
+            hits: 1,
+            min_length: 6,
+            both_strands: true,
+            ..Default::default()
+        };
+
+        let results = motif_finder(&seqs, &config).unwrap();
+        let refined = refine_results(results, &seqs, &config, &EmConfig::default()).unwrap();
+
+        let width = refined[0].pfm.len();
+        let matched = refined[0]
+            .sites
+            .iter()
+            .map(|site| {
+                let matched = seqs[site.sequence].slice(site.start..site.start + width);
+                match site.strand {
+                    Strand::Forward => matched,
+                    Strand::Reverse => matched.reverse_complement(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(Pfm::from_sequences(&matched), refined[0].pfm);
+        assert_eq!(refined[0].sites.len(), seqs.len());
+        assert!(refined[0].log_likelihood.is_some());
+    }
+
+    #[test]
+    fn test_discriminative_motif_finder() {
+        // Both motifs are in every sequence, but only GGCCTTAA is absent from the controls
+        let seqs = [
+            Sequence::from("AGATTACATGGCCTTAAC"),
+            Sequence::from("TGGCCTTAACAGATTACAT"),
+            Sequence::from("GAGATTACAATGGCCTTAAT"),
+            Sequence::from("CGGCCTTAAGGAGATTACAC"),
+        ];
+        let controls = [
+            Sequence::from("TTAGATTACACCTGCA"),
+            Sequence::from("GCAGATTACATCCAGT"),
+            Sequence::from("ACTGAGATTACACGCA"),
+            Sequence::from("CCAGATTACAATGCGT"),
+        ];
+        let config = SearchConfig {
+            hits: 1,
+            min_length: 6,
+            ..Default::default()
+        };
+
+        let results = discriminative_motif_finder(&seqs, &controls, &config).unwrap();
+
+        let enrichment = results[0].enrichment.unwrap();
+        assert!(results[0].consensus.contains("CCTTAA"), "{:?}", results);
+        assert_eq!(enrichment.positives, 4);
+        assert_eq!(enrichment.controls, 0);
+        assert!(discriminative_motif_finder(&seqs, &[], &config).is_err());
+    }
+
     #[test]
     fn test_get_all_shift_pfms_with_pfm_longer_seq() {
-        let pfm = Pfm::from_sequences(&[Sequence::from("ACGT"), Sequence::from("ACGT")]);
+        let alignment = Alignment {
+            pfm: Pfm::from_sequences(&[Sequence::from("ACGT"), Sequence::from("ACGT")]),
+            sites: vec![],
+        };
         let seq = Sequence::from("ACGTA");
 
-        let res = get_all_shift_pfms_with_pfm(&pfm, &seq, 2);
+        let res = get_all_shift_pfms_with_pfm(&alignment, &seq, 2, Strand::Forward, 2);
 
         assert_eq!(res.len(), 6);
     }
 
     #[test]
     fn test_get_all_shift_pfms_with_pfm_shorter_seq() {
-        let pfm = Pfm::from_sequences(&[Sequence::from("ACGT"), Sequence::from("ACGT")]);
+        let alignment = Alignment {
+            pfm: Pfm::from_sequences(&[Sequence::from("ACGT"), Sequence::from("ACGT")]),
+            sites: vec![],
+        };
         let seq = Sequence::from("ACG");
 
-        let res = get_all_shift_pfms_with_pfm(&pfm, &seq, 2);
+        let res = get_all_shift_pfms_with_pfm(&alignment, &seq, 2, Strand::Forward, 2);
 
         assert_eq!(res.len(), 4);
     }
@@ -390,39 +1533,56 @@ mod tests {
 
         let clipped_pfms = pfms
             .into_iter()
-            .flat_map(|x| extraxt_high_interest_area(x, 0.5, 4, 20, Some(1)))
+            .flat_map(|x| {
+                extraxt_high_interest_area(x, 0.5, 4, 20, Some(1), &Background::uniform())
+            })
             .sorted_by(|a, b| {
-                b.get_custom_score()
-                    .partial_cmp(&a.get_custom_score())
+                b.pfm
+                    .get_custom_score(&Background::uniform())
+                    .partial_cmp(&a.pfm.get_custom_score(&Background::uniform()))
                     .unwrap_or_else(|| {
-                        panic!("{:?} {:?}", a.get_custom_score(), b.get_custom_score())
+                        panic!(
+                            "{:?} {:?}",
+                            a.pfm.get_custom_score(&Background::uniform()),
+                            b.pfm.get_custom_score(&Background::uniform())
+                        )
                     })
             })
             .collect::<Vec<_>>();
 
         assert_eq!(
-            clipped_pfms[0].get_consensus_string(),
+            clipped_pfms[0].pfm.get_consensus_string(),
             String::from("AACCGGTT")
         );
     }
 
     #[test]
     fn test_extract_high_interest_area_on_already_high_interest_area() {
-        let pfm = Pfm::from_sequences(&[Sequence::from("ACGT"), Sequence::from("ACGT")]);
+        let alignment = Alignment {
+            pfm: Pfm::from_sequences(&[Sequence::from("ACGT"), Sequence::from("ACGT")]),
+            sites: vec![],
+        };
 
-        let res = extraxt_high_interest_area(pfm, 0.5, 2, 20, Some(1));
+        let res =
+            extraxt_high_interest_area(alignment, 0.5, 2, 20, Some(1), &Background::uniform());
 
         let best = res
             .into_iter()
             .sorted_by(|a, b| {
-                b.get_custom_score()
-                    .partial_cmp(&a.get_custom_score())
+                b.pfm
+                    .get_custom_score(&Background::uniform())
+                    .partial_cmp(&a.pfm.get_custom_score(&Background::uniform()))
                     .unwrap_or_else(|| {
-                        panic!("{:?} {:?}", a.get_custom_score(), b.get_custom_score())
+                        panic!(
+                            "{:?} {:?}",
+                            a.pfm.get_custom_score(&Background::uniform()),
+                            b.pfm.get_custom_score(&Background::uniform())
+                        )
                     })
             })
             .next()
             .unwrap()
+            .pfm
             .get_consensus_string();
 
         assert_eq!(best, "ACGT");
@@ -0,0 +1,143 @@
+//! Plain count matrices: an optional `>name` line followed by one line per position with the
+//! counts of A, C, G and T. Several motifs can follow each other, each starting with its name.
+//!
+//! ```text
+//! >GATA
+//! 0 0 10 0
+//! 10 0 0 0
+//! 0 0 0 10
+//! 9 0 1 0
+//! ```
+
+use std::io::{BufRead, Write};
+
+use super::{check_row, Motif};
+use crate::{datastructures::pfm::Pfm, Error, Result};
+
+/// Reads every motif from `reader`. Lines starting with `#` are comments.
+pub fn read_counts(reader: impl BufRead) -> Result<Vec<Motif>> {
+    let mut motifs = Vec::new();
+    let mut name = None;
+    let mut rows = Vec::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('>') {
+            push_motif(&mut motifs, name.take(), std::mem::take(&mut rows));
+            name = Some(header.trim().to_string());
+            continue;
+        }
+
+        let parse_error = |message: String| Error::Parse {
+            line: i + 1,
+            message,
+        };
+
+        let counts = line
+            .split_whitespace()
+            .map(|count| {
+                count
+                    .parse::<usize>()
+                    .map_err(|e| parse_error(format!("invalid count {:?}: {}", count, e)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let row: [usize; 4] = counts.try_into().map_err(|counts: Vec<_>| {
+            parse_error(format!(
+                "expected the counts of A, C, G and T, got {} numbers",
+                counts.len()
+            ))
+        })?;
+        check_row(&row.map(|count| count as f64)).map_err(parse_error)?;
+
+        rows.push(row);
+    }
+
+    push_motif(&mut motifs, name, rows);
+    Ok(motifs)
+}
+
+/// Adds the motif made of `rows`, named after its position in the file when it has no name.
+fn push_motif(motifs: &mut Vec<Motif>, name: Option<String>, rows: Vec<[usize; 4]>) {
+    if rows.is_empty() {
+        return;
+    }
+
+    motifs.push(Motif {
+        name: name.unwrap_or_else(|| format!("motif_{}", motifs.len() + 1)),
+        pfm: from_rows(rows),
+        log_e_value: None,
+    });
+}
+
+/// A pfm of count rows. The sample size is the largest row total, as files may round counts.
+pub(crate) fn from_rows(matrix: Vec<[usize; 4]>) -> Pfm {
+    let sample_size = matrix.iter().map(|row| row.iter().sum()).max().unwrap_or(0);
+
+    Pfm {
+        matrix,
+        sample_size,
+    }
+}
+
+/// Writes `motifs` so [`read_counts`] reads them back.
+pub fn write_counts(mut writer: impl Write, motifs: &[Motif]) -> Result<()> {
+    for motif in motifs {
+        writeln!(writer, ">{}", motif.name)?;
+        for row in motif.pfm.matrix.iter() {
+            writeln!(writer, "{}\t{}\t{}\t{}", row[0], row[1], row[2], row[3])?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datastructures::sequence::Sequence;
+
+    #[test]
+    fn test_read_write_counts() {
+        let motifs = vec![
+            Motif {
+                name: "first".to_string(),
+                pfm: Pfm::from_sequences(&[Sequence::from("GATA"), Sequence::from("GATT")]),
+                log_e_value: None,
+            },
+            Motif {
+                name: "second".to_string(),
+                pfm: Pfm::from_sequences(&[Sequence::from("CC")]),
+                log_e_value: None,
+            },
+        ];
+
+        let mut written = Vec::new();
+        write_counts(&mut written, &motifs).unwrap();
+
+        assert_eq!(read_counts(written.as_slice()).unwrap(), motifs);
+    }
+
+    #[test]
+    fn test_read_counts_without_name() {
+        let motifs = read_counts("# comment\n0 0 2 0\n2 0 0 0\n".as_bytes()).unwrap();
+
+        assert_eq!(motifs.len(), 1);
+        assert_eq!(motifs[0].name, "motif_1");
+        assert_eq!(motifs[0].pfm.get_consensus_string(), "GA");
+        assert_eq!(motifs[0].pfm.sample_size, 2);
+    }
+
+    #[test]
+    fn test_read_counts_invalid_row() {
+        let err = read_counts(">a\n1 2 3\n".as_bytes()).unwrap_err();
+        assert!(matches!(err, Error::Parse { line: 2, .. }), "{:?}", err);
+
+        let err = read_counts(">a\n1 2 3 4\n0 0 0 0\n".as_bytes()).unwrap_err();
+        assert!(matches!(err, Error::Parse { line: 3, .. }), "{:?}", err);
+    }
+}
@@ -0,0 +1,234 @@
+//! HOMER `.motif` files: a tab separated header with the consensus, the name and the log-odds
+//! detection threshold of the motif, followed by one line of base probabilities per position.
+//!
+//! ```text
+//! >GATA    GATA-factor    5.2
+//! 0.001    0.001    0.997    0.001
+//! 0.997    0.001    0.001    0.001
+//! ```
+
+use std::io::{BufRead, Write};
+
+use super::{check_row, from_probabilities, Motif, DEFAULT_SITES};
+use crate::{
+    datastructures::{
+        background::Background, pfm::Pfm, pwm::Pwm, score_distribution::ScoreDistribution,
+    },
+    Error, Result,
+};
+
+/// Probability HOMER gives bases never seen at a position, as log-odds of zero are undefined
+const MIN_PROBABILITY: f64 = 0.001;
+
+/// A motif with the log-odds score a window must reach to be a site.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HomerMotif {
+    pub motif: Motif,
+    /// Sum of the natural log of each base probability over 0.25
+    pub threshold: f64,
+}
+
+impl HomerMotif {
+    /// Gives `motif` the threshold that windows from the background reach with `p_value`, capped
+    /// at the highest score so the best match is always a site.
+    pub fn with_p_value(motif: Motif, p_value: f64) -> Self {
+        let pwm = log_odds(&probabilities(&motif.pfm));
+        let distribution = ScoreDistribution::new(&pwm, &Background::uniform());
+
+        Self {
+            threshold: distribution.score(p_value).min(pwm.max_score()),
+            motif,
+        }
+    }
+}
+
+/// The probabilities of the pfm as HOMER writes them, with no base below [`MIN_PROBABILITY`].
+fn probabilities(pfm: &Pfm) -> Pwm {
+    let mut ppm = Pwm::pfm_into_ppm(pfm.clone());
+    for row in ppm.matrix.iter_mut() {
+        let total = row.iter().map(|p| p.max(MIN_PROBABILITY)).sum::<f64>();
+        *row = row.map(|p| p.max(MIN_PROBABILITY) / total);
+    }
+
+    ppm
+}
+
+/// HOMER's log-odds scores, natural logs against a uniform background.
+fn log_odds(ppm: &Pwm) -> Pwm {
+    Pwm {
+        matrix: ppm
+            .matrix
+            .iter()
+            .map(|row| row.map(|p| (p / 0.25).ln()))
+            .collect(),
+        sample_size: ppm.sample_size,
+    }
+}
+
+/// Reads every motif from a HOMER file. HOMER files do not store the amount of sites, so the
+/// counts are the probabilities of 20 sites. The thresholds are natural log-odds against a
+/// uniform background with HOMER's pseudocounts, so they do not carry over to [`Pwm::log_odds`]
+/// scores.
+pub fn read_homer(reader: impl BufRead) -> Result<Vec<HomerMotif>> {
+    let mut motifs: Vec<HomerMotif> = Vec::new();
+    let mut rows = Vec::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let parse_error = |message: String| Error::Parse {
+            line: i + 1,
+            message,
+        };
+
+        if let Some(header) = line.strip_prefix('>') {
+            finish_motif(motifs.last_mut(), std::mem::take(&mut rows));
+
+            let fields = header.split('\t').collect::<Vec<_>>();
+            let [consensus, name, threshold, ..] = fields[..] else {
+                return Err(parse_error(
+                    "expected a consensus, name and threshold separated by tabs".to_string(),
+                ));
+            };
+            let threshold = threshold
+                .trim()
+                .parse::<f64>()
+                .map_err(|e| parse_error(format!("invalid threshold {:?}: {}", threshold, e)))?;
+            let name = if name.trim().is_empty() {
+                consensus
+            } else {
+                name
+            };
+
+            motifs.push(HomerMotif {
+                motif: Motif {
+                    name: name.trim().to_string(),
+                    pfm: Pfm {
+                        matrix: Vec::new(),
+                        sample_size: 0,
+                    },
+                    log_e_value: None,
+                },
+                threshold,
+            });
+            continue;
+        }
+
+        if motifs.is_empty() {
+            return Err(parse_error("probabilities before any header".to_string()));
+        }
+
+        let row = line
+            .split_whitespace()
+            .map(|p| {
+                p.parse::<f64>()
+                    .map_err(|e| parse_error(format!("invalid probability {:?}: {}", p, e)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let row: [f64; 4] = row.try_into().map_err(|row: Vec<_>| {
+            parse_error(format!("expected 4 probabilities, got {}", row.len()))
+        })?;
+        check_row(&row).map_err(parse_error)?;
+        rows.push(row);
+    }
+
+    finish_motif(motifs.last_mut(), rows);
+
+    if let Some(motif) = motifs.iter().find(|motif| motif.motif.pfm.is_empty()) {
+        return Err(Error::EmptyInput(format!(
+            "motif {} has no probabilities",
+            motif.motif.name
+        )));
+    }
+
+    Ok(motifs)
+}
+
+fn finish_motif(motif: Option<&mut HomerMotif>, rows: Vec<[f64; 4]>) {
+    if let Some(motif) = motif {
+        motif.motif.pfm = from_probabilities(&rows, DEFAULT_SITES);
+    }
+}
+
+/// Writes `motifs` so [`read_homer`] and HOMER read them back.
+pub fn write_homer(mut writer: impl Write, motifs: &[HomerMotif]) -> Result<()> {
+    for HomerMotif { motif, threshold } in motifs {
+        writeln!(
+            writer,
+            ">{}\t{}\t{:.6}",
+            motif.pfm.get_consensus_string(),
+            motif.name,
+            threshold
+        )?;
+        for row in probabilities(&motif.pfm).matrix {
+            writeln!(
+                writer,
+                "{:.3}\t{:.3}\t{:.3}\t{:.3}",
+                row[0], row[1], row[2], row[3]
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datastructures::sequence::Sequence;
+
+    #[test]
+    fn test_read_write_homer() {
+        let motif = Motif {
+            name: "GATA-factor".to_string(),
+            pfm: Pfm::from_sequences(&vec![Sequence::from("GATA"); 20]),
+            log_e_value: None,
+        };
+        let motifs = vec![HomerMotif::with_p_value(motif.clone(), 1e-3)];
+
+        let mut written = Vec::new();
+        write_homer(&mut written, &motifs).unwrap();
+        let read = read_homer(written.as_slice()).unwrap();
+
+        assert_eq!(read[0].motif, motif);
+        assert!((read[0].threshold - motifs[0].threshold).abs() < 1e-6);
+        assert!(String::from_utf8(written)
+            .unwrap()
+            .starts_with(">GATA\tGATA-factor\t"));
+    }
+
+    #[test]
+    fn test_with_p_value() {
+        let motif = Motif {
+            name: "GATTACA".to_string(),
+            pfm: Pfm::from_sequences(&[Sequence::from("GATTACA")]),
+            log_e_value: None,
+        };
+        let max_score = log_odds(&probabilities(&motif.pfm)).max_score();
+
+        // Only the best match is rarer than this
+        let threshold = HomerMotif::with_p_value(motif.clone(), 1e-4).threshold;
+        assert!((threshold - max_score).abs() < 0.05, "{}", threshold);
+        assert!(HomerMotif::with_p_value(motif, 0.01).threshold < max_score);
+    }
+
+    #[test]
+    fn test_read_homer_invalid_header() {
+        let err = read_homer(">GATA\n0.25 0.25 0.25 0.25\n".as_bytes()).unwrap_err();
+
+        assert!(matches!(err, Error::Parse { line: 1, .. }), "{:?}", err);
+    }
+
+    #[test]
+    fn test_read_homer_empty_position() {
+        let input = ">GA\tGA\t3.0\n0.001 0.001 0.997 0.001\n0 0 0 0\n";
+
+        let err = read_homer(input.as_bytes()).unwrap_err();
+
+        assert!(matches!(err, Error::Parse { line: 3, .. }), "{:?}", err);
+    }
+}
@@ -0,0 +1,232 @@
+//! JASPAR count matrices, with one line per base and one column per position. The `.jaspar`
+//! flavour labels each line with its base and brackets the counts, the raw `.pfm` flavour only
+//! has the four lines of counts.
+//!
+//! ```text
+//! >MA0004.1 Arnt
+//! A  [ 4 19  0  0  0  0 ]
+//! C  [16  0 20  0  0  0 ]
+//! G  [ 0  1  0 20  0 20 ]
+//! T  [ 0  0  0  0 20  0 ]
+//! ```
+
+use std::io::{BufRead, Write};
+
+use super::{check_row, from_weights, Motif};
+use crate::{Error, Result};
+
+/// Reads every motif from a JASPAR file of either flavour. Lines starting with `#` are
+/// comments, and a raw `.pfm` matrix may come without a header.
+pub fn read_jaspar(reader: impl BufRead) -> Result<Vec<Motif>> {
+    let mut motifs = Vec::new();
+    let mut name = None;
+    let mut lines: Vec<Vec<f64>> = Vec::new();
+    let mut header_line = 1;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('>') {
+            push_motif(
+                &mut motifs,
+                name.take(),
+                std::mem::take(&mut lines),
+                header_line,
+            )?;
+            // The matrix ID comes first, followed by the name of the factor
+            name = header.split_whitespace().next().map(str::to_string);
+            header_line = i + 1;
+            continue;
+        }
+
+        let parse_error = |message: String| Error::Parse {
+            line: i + 1,
+            message,
+        };
+
+        // Strip the base label and brackets of the .jaspar flavour
+        let counts = match line.split_once('[') {
+            Some((base, counts)) => {
+                let expected = ['A', 'C', 'G', 'T'][lines.len().min(3)];
+                if !base.trim().eq_ignore_ascii_case(&expected.to_string()) {
+                    return Err(parse_error(format!(
+                        "expected the counts of {}, got {:?}",
+                        expected,
+                        base.trim()
+                    )));
+                }
+                counts.trim_end().trim_end_matches(']')
+            }
+            None => line,
+        };
+
+        lines.push(
+            counts
+                .split_whitespace()
+                .map(|count| {
+                    count
+                        .parse::<f64>()
+                        .map_err(|e| parse_error(format!("invalid count {:?}: {}", count, e)))
+                })
+                .collect::<Result<Vec<_>>>()?,
+        );
+    }
+
+    push_motif(&mut motifs, name, lines, header_line)?;
+    Ok(motifs)
+}
+
+/// Adds the motif of the four base `lines`, which are turned into rows of positions.
+fn push_motif(
+    motifs: &mut Vec<Motif>,
+    name: Option<String>,
+    lines: Vec<Vec<f64>>,
+    header_line: usize,
+) -> Result<()> {
+    if lines.is_empty() {
+        return Ok(());
+    }
+
+    let parse_error = |message: String| Error::Parse {
+        line: header_line,
+        message,
+    };
+    if lines.len() != 4 {
+        return Err(parse_error(format!(
+            "expected 4 lines of counts, got {}",
+            lines.len()
+        )));
+    }
+    let width = lines[0].len();
+    if lines.iter().any(|line| line.len() != width) {
+        return Err(parse_error(
+            "the lines of counts differ in length".to_string(),
+        ));
+    }
+
+    let rows = (0..width)
+        .map(|i| [0, 1, 2, 3].map(|base| lines[base][i]))
+        .collect::<Vec<_>>();
+    for (i, row) in rows.iter().enumerate() {
+        check_row(row)
+            .map_err(|message| parse_error(format!("position {}: {}", i + 1, message)))?;
+    }
+
+    motifs.push(Motif {
+        name: name.unwrap_or_else(|| format!("motif_{}", motifs.len() + 1)),
+        pfm: from_weights(&rows),
+        log_e_value: None,
+    });
+    Ok(())
+}
+
+/// Writes `motifs` in the `.jaspar` flavour, with the name as both the ID and name of each.
+pub fn write_jaspar(mut writer: impl Write, motifs: &[Motif]) -> Result<()> {
+    for motif in motifs {
+        writeln!(writer, ">{}\t{}", motif.name, motif.name)?;
+        for (base, label) in ['A', 'C', 'G', 'T'].iter().enumerate() {
+            let counts = motif
+                .pfm
+                .matrix
+                .iter()
+                .map(|row| format!("{:>4}", row[base]))
+                .collect::<String>();
+            writeln!(writer, "{}  [{} ]", label, counts)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes `motifs` in the raw `.pfm` flavour, with a header line for each.
+pub fn write_jaspar_pfm(mut writer: impl Write, motifs: &[Motif]) -> Result<()> {
+    for motif in motifs {
+        writeln!(writer, ">{}", motif.name)?;
+        for base in 0..4 {
+            let counts = motif
+                .pfm
+                .matrix
+                .iter()
+                .map(|row| row[base].to_string())
+                .collect::<Vec<_>>();
+            writeln!(writer, "{}", counts.join("\t"))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datastructures::{pfm::Pfm, sequence::Sequence};
+
+    fn motifs() -> Vec<Motif> {
+        vec![
+            Motif {
+                name: "MA0001.1".to_string(),
+                pfm: Pfm::from_sequences(&[Sequence::from("GATA"), Sequence::from("GATT")]),
+                log_e_value: None,
+            },
+            Motif {
+                name: "MA0002.1".to_string(),
+                pfm: Pfm::from_sequences(&[Sequence::from("CCG")]),
+                log_e_value: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_read_write_jaspar() {
+        let mut written = Vec::new();
+        write_jaspar(&mut written, &motifs()).unwrap();
+
+        assert_eq!(read_jaspar(written.as_slice()).unwrap(), motifs());
+    }
+
+    #[test]
+    fn test_read_write_jaspar_pfm() {
+        let mut written = Vec::new();
+        write_jaspar_pfm(&mut written, &motifs()).unwrap();
+
+        assert_eq!(read_jaspar(written.as_slice()).unwrap(), motifs());
+    }
+
+    #[test]
+    fn test_read_jaspar_example() {
+        let input = ">MA0004.1\tArnt\nA  [ 4 19  0  0  0  0 ]\nC  [16  0 20  0  0  0 ]\n\
+            G  [ 0  1  0 20  0 20 ]\nT  [ 0  0  0  0 20  0 ]\n";
+
+        let motifs = read_jaspar(input.as_bytes()).unwrap();
+
+        assert_eq!(motifs[0].name, "MA0004.1");
+        assert_eq!(motifs[0].pfm.get_consensus_string(), "CACGTG");
+        assert_eq!(motifs[0].pfm.sample_size, 20);
+    }
+
+    #[test]
+    fn test_read_jaspar_frequencies() {
+        let input = ">a\nA [ 0.1 0.0 ]\nC [ 0.1 0.0 ]\nG [ 0.7 0.0 ]\nT [ 0.1 1.0 ]\n";
+
+        let motifs = read_jaspar(input.as_bytes()).unwrap();
+
+        assert_eq!(motifs[0].pfm.matrix, vec![[2, 2, 14, 2], [0, 0, 0, 20]]);
+        assert_eq!(motifs[0].pfm.sample_size, 20);
+
+        let err = read_jaspar(">a\n0.1 0\n0.1 0\n0.7 0\n0.1 0\n".as_bytes()).unwrap_err();
+        assert!(matches!(err, Error::Parse { line: 1, .. }), "{:?}", err);
+    }
+
+    #[test]
+    fn test_read_jaspar_missing_line() {
+        let input = ">a\n1 0\n0 1\n0 0\n";
+
+        let err = read_jaspar(input.as_bytes()).unwrap_err();
+
+        assert!(matches!(err, Error::Parse { line: 1, .. }), "{:?}", err);
+    }
+}
@@ -0,0 +1,370 @@
+//! The MEME minimal motif format, as read by Tomtom, FIMO and SEA.
+//!
+//! ```text
+//! MEME version 4
+//!
+//! ALPHABET= ACGT
+//!
+//! strands: + -
+//!
+//! Background letter frequencies
+//! A 0.25 C 0.25 G 0.25 T 0.25
+//!
+//! MOTIF GATA
+//! letter-probability matrix: alength= 4 w= 4 nsites= 10 E= 1.2e-3
+//!  0.000000 0.000000 1.000000 0.000000
+//!  ...
+//! ```
+
+use std::io::{BufRead, Write};
+
+use super::{check_row, from_probabilities, Motif, DEFAULT_SITES};
+use crate::{
+    datastructures::{background::Background, pfm::Pfm, pwm::Pwm},
+    stats, Error, Result,
+};
+
+/// The contents of a MEME motif file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemeFile {
+    pub motifs: Vec<Motif>,
+    /// The background letter frequencies, if the file has them
+    pub background: Option<Background>,
+    /// Whether the motifs were searched for on both strands
+    pub both_strands: bool,
+}
+
+/// Reads every motif from a MEME file. The counts of each motif are its probabilities times its
+/// `nsites`.
+pub fn read_meme(reader: impl BufRead) -> Result<MemeFile> {
+    let mut lines = reader.lines().enumerate().peekable();
+    let mut file = MemeFile {
+        motifs: Vec::new(),
+        background: None,
+        both_strands: false,
+    };
+
+    let mut has_version = false;
+    while let Some((i, line)) = lines.next() {
+        let line = line?;
+        let line = line.trim();
+        let parse_error = |message: String| Error::Parse {
+            line: i + 1,
+            message,
+        };
+
+        if line.starts_with("MEME version") {
+            has_version = true;
+        } else if let Some(alphabet) = line.strip_prefix("ALPHABET=") {
+            if alphabet.trim() != "ACGT" {
+                return Err(parse_error(format!(
+                    "only the ACGT alphabet is supported, got {}",
+                    alphabet.trim()
+                )));
+            }
+        } else if let Some(strands) = line.strip_prefix("strands:") {
+            file.both_strands = strands.contains('-');
+        } else if line.starts_with("Background letter frequencies") {
+            // The frequencies follow on the next lines, up to an empty line
+            let mut words = Vec::new();
+            while let Some((_, line)) = lines.next_if(|(_, line)| {
+                line.as_ref()
+                    .is_ok_and(|line| !line.trim().is_empty() && !line.starts_with("MOTIF"))
+            }) {
+                words.extend(line?.split_whitespace().map(str::to_string));
+            }
+
+            file.background = Some(parse_background(&words).map_err(parse_error)?);
+        } else if let Some(name) = line.strip_prefix("MOTIF") {
+            // The name can be followed by an alternative name, which is not kept
+            let name = name.split_whitespace().next().unwrap_or_default();
+            file.motifs.push(Motif {
+                name: name.to_string(),
+                pfm: Pfm {
+                    matrix: Vec::new(),
+                    sample_size: 0,
+                },
+                log_e_value: None,
+            });
+        } else if let Some(header) = line.strip_prefix("letter-probability matrix:") {
+            let motif = file
+                .motifs
+                .last_mut()
+                .ok_or_else(|| parse_error("matrix before any MOTIF line".to_string()))?;
+            let (width, sites, log_e_value) = parse_matrix_header(header).map_err(parse_error)?;
+
+            let mut rows = Vec::new();
+            while rows.len() < width {
+                let (i, line) = lines.next().ok_or_else(|| Error::Parse {
+                    line: i + 1,
+                    message: format!("expected {} matrix rows, got {}", width, rows.len()),
+                })?;
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                rows.push(parse_row(&line).map_err(|message| Error::Parse {
+                    line: i + 1,
+                    message,
+                })?);
+            }
+
+            motif.pfm = from_probabilities(&rows, sites.unwrap_or(DEFAULT_SITES));
+            motif.log_e_value = log_e_value;
+        }
+    }
+
+    if !has_version {
+        return Err(Error::Parse {
+            line: 1,
+            message: "expected a 'MEME version' line".to_string(),
+        });
+    }
+    if let Some(motif) = file.motifs.iter().find(|motif| motif.pfm.is_empty()) {
+        return Err(Error::EmptyInput(format!(
+            "motif {} has no letter-probability matrix",
+            motif.name
+        )));
+    }
+
+    Ok(file)
+}
+
+fn parse_background(words: &[String]) -> std::result::Result<Background, String> {
+    let mut frequencies = [None; 4];
+    for pair in words.chunks(2) {
+        let [letter, frequency] = pair else {
+            return Err("expected pairs of a letter and a frequency".to_string());
+        };
+        let index = ["A", "C", "G", "T"]
+            .iter()
+            .position(|base| base == letter)
+            .ok_or_else(|| format!("unknown letter {:?} in the background", letter))?;
+        frequencies[index] = Some(
+            frequency
+                .parse::<f64>()
+                .map_err(|e| format!("invalid frequency {:?}: {}", frequency, e))?,
+        );
+    }
+
+    let frequencies = frequencies
+        .into_iter()
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| "background is missing a letter".to_string())?;
+    Background::from_frequencies(frequencies.try_into().unwrap()).map_err(|e| e.to_string())
+}
+
+/// Reads the width, sites and E-value from the `key= value` pairs of a matrix header.
+fn parse_matrix_header(
+    header: &str,
+) -> std::result::Result<(usize, Option<usize>, Option<f64>), String> {
+    let words = header.split_whitespace().collect::<Vec<_>>();
+    let value = |key: &str| {
+        words
+            .iter()
+            .position(|word| *word == key)
+            .and_then(|i| words.get(i + 1))
+    };
+
+    if let Some(alength) = value("alength=") {
+        if *alength != "4" {
+            return Err(format!("expected an alphabet length of 4, got {}", alength));
+        }
+    }
+
+    let width = value("w=")
+        .ok_or_else(|| "matrix header has no width".to_string())?
+        .parse::<usize>()
+        .map_err(|e| format!("invalid width: {}", e))?;
+    let sites = value("nsites=")
+        .map(|sites| sites.parse::<f64>().map(|sites| sites.round() as usize))
+        .transpose()
+        .map_err(|e| format!("invalid nsites: {}", e))?
+        // Files with less than one site tell nothing about the amount of sites
+        .filter(|&sites| sites > 0);
+    let log_e_value = value("E=")
+        .map(|e_value| {
+            stats::ln_from_scientific(e_value)
+                .ok_or_else(|| format!("invalid E-value {:?}", e_value))
+        })
+        .transpose()?;
+
+    Ok((width, sites, log_e_value))
+}
+
+fn parse_row(line: &str) -> std::result::Result<[f64; 4], String> {
+    let row = line
+        .split_whitespace()
+        .map(|p| {
+            p.parse::<f64>()
+                .map_err(|e| format!("invalid probability {:?}: {}", p, e))
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let row = row
+        .try_into()
+        .map_err(|row: Vec<_>| format!("expected 4 probabilities, got {}", row.len()))?;
+    check_row(&row)?;
+
+    Ok(row)
+}
+
+/// Writes `motifs` with the background they were scored against, so [`read_meme`] and the MEME
+/// suite read them back.
+pub fn write_meme(
+    mut writer: impl Write,
+    motifs: &[Motif],
+    background: &Background,
+    both_strands: bool,
+) -> Result<()> {
+    writeln!(writer, "MEME version 4")?;
+    writeln!(writer)?;
+    writeln!(writer, "ALPHABET= ACGT")?;
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        "strands: {}",
+        if both_strands { "+ -" } else { "+" }
+    )?;
+    writeln!(writer)?;
+    writeln!(writer, "Background letter frequencies")?;
+    writeln!(
+        writer,
+        "A {:.6} C {:.6} G {:.6} T {:.6}",
+        background.frequencies[0],
+        background.frequencies[1],
+        background.frequencies[2],
+        background.frequencies[3]
+    )?;
+
+    for motif in motifs {
+        writeln!(writer)?;
+        writeln!(writer, "MOTIF {}", motif.name)?;
+        write!(
+            writer,
+            "letter-probability matrix: alength= 4 w= {} nsites= {}",
+            motif.pfm.len(),
+            motif.pfm.sample_size
+        )?;
+        if let Some(log_e_value) = motif.log_e_value {
+            write!(writer, " E= {}", stats::ln_to_scientific(log_e_value, 1))?;
+        }
+        writeln!(writer)?;
+
+        for row in Pwm::pfm_into_ppm(motif.pfm.clone()).matrix {
+            writeln!(
+                writer,
+                " {:.6}\t{:.6}\t{:.6}\t{:.6}",
+                row[0], row[1], row[2], row[3]
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datastructures::sequence::Sequence;
+
+    #[test]
+    fn test_read_write_meme() {
+        let motifs = vec![
+            Motif {
+                name: "GATA".to_string(),
+                pfm: Pfm::from_sequences(&[
+                    Sequence::from("GATA"),
+                    Sequence::from("GATT"),
+                    Sequence::from("CATA"),
+                ]),
+                log_e_value: Some(1.5e-3f64.ln()),
+            },
+            Motif {
+                name: "CC".to_string(),
+                pfm: Pfm::from_sequences(&[Sequence::from("CC")]),
+                log_e_value: None,
+            },
+        ];
+        let background = Background::from_frequencies([0.3, 0.2, 0.2, 0.3]).unwrap();
+
+        let mut written = Vec::new();
+        write_meme(&mut written, &motifs, &background, true).unwrap();
+        let file = read_meme(written.as_slice()).unwrap();
+
+        for (read, motif) in file.motifs.iter().zip(motifs.iter()) {
+            assert_eq!((&read.name, &read.pfm), (&motif.name, &motif.pfm));
+            match (read.log_e_value, motif.log_e_value) {
+                (Some(read), Some(expected)) => assert!((read - expected).abs() < 1e-9),
+                (read, expected) => assert_eq!(read, expected),
+            }
+        }
+        assert!(file.both_strands);
+        let read_background = file.background.unwrap();
+        for (read, expected) in read_background.frequencies.iter().zip([0.3, 0.2, 0.2, 0.3]) {
+            assert!((read - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_write_meme_tiny_e_value() {
+        let motifs = vec![Motif {
+            name: "GATA".to_string(),
+            pfm: Pfm::from_sequences(&[Sequence::from("GATA")]),
+            log_e_value: Some(-1244.8),
+        }];
+
+        let mut written = Vec::new();
+        write_meme(&mut written, &motifs, &Background::uniform(), false).unwrap();
+
+        assert!(String::from_utf8(written.clone())
+            .unwrap()
+            .contains(" E= 2.5e-541\n"));
+        let read = read_meme(written.as_slice()).unwrap().motifs[0].log_e_value;
+        assert!((read.unwrap() - -1244.8).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_read_meme_without_nsites() {
+        let input = "MEME version 5\n\nALPHABET= ACGT\n\nMOTIF MA0001.1 AGL3\n\
+            letter-probability matrix: alength= 4 w= 2\n\
+            0.5 0.5 0 0\n 0 0 0 1\n";
+
+        let file = read_meme(input.as_bytes()).unwrap();
+
+        assert_eq!(file.background, None);
+        assert_eq!(file.motifs[0].name, "MA0001.1");
+        assert_eq!(
+            file.motifs[0].pfm.matrix,
+            vec![[10, 10, 0, 0], [0, 0, 0, 20]]
+        );
+    }
+
+    #[test]
+    fn test_read_meme_few_sites() {
+        let input = "MEME version 4\nMOTIF a\nletter-probability matrix: w= 2 nsites= 0\n\
+            0.3 0.3 0.2 0.2\n0 0 0 1\nMOTIF b\nletter-probability matrix: w= 1 nsites= 1\n\
+            0.3 0.3 0.2 0.2\n";
+
+        let file = read_meme(input.as_bytes()).unwrap();
+
+        assert_eq!(file.motifs[0].pfm.matrix, vec![[6, 6, 4, 4], [0, 0, 0, 20]]);
+        // Every position keeps its one site
+        assert_eq!(file.motifs[1].pfm.matrix, vec![[1, 0, 0, 0]]);
+
+        let input = "MEME version 4\nMOTIF a\nletter-probability matrix: w= 1\n0 0 0 0\n";
+        let err = read_meme(input.as_bytes()).unwrap_err();
+        assert!(matches!(err, Error::Parse { line: 4, .. }), "{:?}", err);
+    }
+
+    #[test]
+    fn test_read_meme_missing_rows() {
+        let input = "MEME version 4\nMOTIF a\nletter-probability matrix: w= 3 nsites= 2\n\
+            1 0 0 0\n";
+
+        let err = read_meme(input.as_bytes()).unwrap_err();
+
+        assert!(matches!(err, Error::Parse { line: 3, .. }), "{:?}", err);
+    }
+}
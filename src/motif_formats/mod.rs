@@ -0,0 +1,213 @@
+//! Reading and writing motifs in files, so found motifs can be used again and by other tools.
+
+use std::{io::Read, path::Path};
+
+use crate::{datastructures::pfm::Pfm, fasta_reader::open_input, Error, Result};
+
+pub mod counts;
+pub mod homer;
+pub mod jaspar;
+pub mod meme;
+pub mod transfac;
+
+/// Amount of sites assumed for motifs stored as probabilities without a count of their sites, as
+/// the MEME suite does
+pub(crate) const DEFAULT_SITES: usize = 20;
+
+/// A named motif, as stored in motif files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Motif {
+    pub name: String,
+    pub pfm: Pfm,
+    /// Natural log of the E-value of the motif, for formats that store one. Strong motifs have
+    /// E-values too small for a float.
+    pub log_e_value: Option<f64>,
+}
+
+/// Format of a motif file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotifFormat {
+    /// Plain count matrices, see [`counts`]
+    Counts,
+    /// MEME minimal motif format, see [`meme`]
+    Meme,
+    /// JASPAR matrices with labelled lines, see [`jaspar`]
+    Jaspar,
+    /// Raw JASPAR matrices, see [`jaspar`]
+    JasparPfm,
+    /// TRANSFAC matrices, see [`transfac`]
+    Transfac,
+    /// HOMER probability matrices with a detection threshold, see [`homer`]
+    Homer,
+}
+
+impl MotifFormat {
+    /// The format of a file with the extension of `path`, if the extension is known. Raw JASPAR
+    /// matrices can only be told apart from count matrices by their `.pfm` extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        match extension.as_str() {
+            "meme" => Some(MotifFormat::Meme),
+            "jaspar" => Some(MotifFormat::Jaspar),
+            "pfm" => Some(MotifFormat::JasparPfm),
+            "transfac" | "dat" => Some(MotifFormat::Transfac),
+            "motif" => Some(MotifFormat::Homer),
+            _ => None,
+        }
+    }
+
+    /// Detects the format from the text of a motif file.
+    pub fn detect(text: &str) -> Self {
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        let first_line = lines.next().unwrap_or_default();
+
+        if first_line.starts_with("MEME version") {
+            MotifFormat::Meme
+        } else if ["AC", "ID", "P0", "PO", "DE", "XX"]
+            .iter()
+            .any(|key| first_line.starts_with(key))
+        {
+            MotifFormat::Transfac
+        } else if first_line.starts_with('>')
+            && first_line
+                .split('\t')
+                .nth(2)
+                .is_some_and(|threshold| threshold.trim().parse::<f64>().is_ok())
+        {
+            MotifFormat::Homer
+        } else if lines.next().is_some_and(|line| line.contains('[')) {
+            MotifFormat::Jaspar
+        } else {
+            MotifFormat::Counts
+        }
+    }
+}
+
+/// Reads every motif in the file at `path`, in the format of its extension or else the detected
+/// [`MotifFormat`]. See [`open_input`](crate::fasta_reader::open_input) for the supported inputs.
+///
+/// Only the matrices are kept, so the detection thresholds of HOMER motifs are dropped. Use
+/// [`homer::read_homer`] to get them.
+pub fn read_motifs(path: &Path) -> Result<Vec<Motif>> {
+    let mut text = String::new();
+    open_input(path)?.read_to_string(&mut text)?;
+
+    let format = MotifFormat::from_path(path).unwrap_or_else(|| MotifFormat::detect(&text));
+    let motifs = match format {
+        MotifFormat::Counts => counts::read_counts(text.as_bytes())?,
+        MotifFormat::Meme => meme::read_meme(text.as_bytes())?.motifs,
+        MotifFormat::Jaspar | MotifFormat::JasparPfm => jaspar::read_jaspar(text.as_bytes())?,
+        MotifFormat::Transfac => transfac::read_transfac(text.as_bytes())?,
+        MotifFormat::Homer => homer::read_homer(text.as_bytes())?
+            .into_iter()
+            .map(|homer| homer.motif)
+            .collect(),
+    };
+
+    if motifs.is_empty() {
+        return Err(Error::EmptyInput(format!(
+            "no motifs in {}",
+            path.display()
+        )));
+    }
+
+    Ok(motifs)
+}
+
+/// Checks that a row of counts or probabilities read from a file has no negative values and is
+/// not all zero, as an empty position has no base probabilities to score with.
+pub(crate) fn check_row(row: &[f64; 4]) -> std::result::Result<(), String> {
+    if row.iter().any(|value| !value.is_finite() || *value < 0.0) {
+        return Err(format!("negative or invalid values in {:?}", row));
+    }
+    if row.iter().sum::<f64>() <= 0.0 {
+        return Err("a position has no counts".to_string());
+    }
+
+    Ok(())
+}
+
+/// A pfm of `sites` sequences, at least one, with the given base probabilities at each position.
+/// The rows, checked by [`check_row`], are scaled to sum to one and rounded so their counts add
+/// up to exactly `sites`, giving the rounding leftovers to the largest remainders.
+pub(crate) fn from_probabilities(rows: &[[f64; 4]], sites: usize) -> Pfm {
+    let sites = sites.max(1);
+
+    counts::from_rows(
+        rows.iter()
+            .map(|row| {
+                let total = row.iter().sum::<f64>();
+                let scaled = row.map(|p| p / total * sites as f64);
+                let mut counts = scaled.map(|count| count.floor() as usize);
+                let left = sites.saturating_sub(counts.iter().sum::<usize>());
+
+                let mut by_remainder = [0, 1, 2, 3];
+                by_remainder.sort_by(|&a, &b| {
+                    let remainder = |i: usize| scaled[i] - counts[i] as f64;
+                    remainder(b).partial_cmp(&remainder(a)).unwrap()
+                });
+                for &i in by_remainder.iter().take(left) {
+                    counts[i] += 1;
+                }
+
+                counts
+            })
+            .collect(),
+    )
+}
+
+/// A pfm of rows of counts, checked by [`check_row`]. Whole counts are kept as they are, while
+/// fractional ones, like frequencies, are taken as the probabilities of as many sites as the
+/// largest row total, and at least [`DEFAULT_SITES`].
+pub(crate) fn from_weights(rows: &[[f64; 4]]) -> Pfm {
+    if rows.iter().flatten().all(|count| count.fract() == 0.0) {
+        return counts::from_rows(
+            rows.iter()
+                .map(|row| row.map(|count| count as usize))
+                .collect(),
+        );
+    }
+
+    let sites = rows
+        .iter()
+        .map(|row| row.iter().sum::<f64>().round() as usize)
+        .max()
+        .unwrap_or(0)
+        .max(DEFAULT_SITES);
+    from_probabilities(rows, sites)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_motif_format_detect() {
+        assert_eq!(
+            MotifFormat::detect("\nMEME version 4\n\nALPHABET= ACGT\n"),
+            MotifFormat::Meme
+        );
+        assert_eq!(MotifFormat::detect(">a\n1 0 0 0\n"), MotifFormat::Counts);
+        assert_eq!(
+            MotifFormat::detect(">MA0004.1 Arnt\nA  [ 4 19 ]\n"),
+            MotifFormat::Jaspar
+        );
+        assert_eq!(
+            MotifFormat::detect("AC  M00001\nXX\n"),
+            MotifFormat::Transfac
+        );
+        assert_eq!(
+            MotifFormat::from_path(Path::new("motifs/MA0004.1.pfm")),
+            Some(MotifFormat::JasparPfm)
+        );
+        assert_eq!(
+            MotifFormat::detect(">GATA\tGATA-factor\t5.2\n0.001\t0.001\t0.997\t0.001\n"),
+            MotifFormat::Homer
+        );
+        assert_eq!(MotifFormat::from_path(Path::new("motifs.txt")), None);
+    }
+}
@@ -0,0 +1,215 @@
+//! TRANSFAC matrices: two letter keys at the start of each line, a `P0` line naming the bases
+//! followed by one numbered line of counts per position, and `//` ending each matrix.
+//!
+//! ```text
+//! AC  M00001
+//! XX
+//! ID  V$MYOD_01
+//! XX
+//! P0      A      C      G      T
+//! 01      1      2      2      0      S
+//! 02      2      1      2      0      R
+//! XX
+//! //
+//! ```
+
+use std::io::{BufRead, Write};
+
+use super::{check_row, from_weights, Motif};
+use crate::{Error, Result};
+
+/// Reads every motif from a TRANSFAC file. Motifs are named by their `ID`, or their accession
+/// (`AC`) if they have no ID.
+pub fn read_transfac(reader: impl BufRead) -> Result<Vec<Motif>> {
+    let mut motifs = Vec::new();
+    let mut motif = PartialMotif::default();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end();
+        let parse_error = |message: String| Error::Parse {
+            line: i + 1,
+            message,
+        };
+
+        if line.starts_with("//") {
+            motif.finish(&mut motifs);
+            continue;
+        }
+
+        let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let value = value.trim();
+        match key {
+            "ID" => motif.id = Some(value.to_string()),
+            "AC" => motif.accession = Some(value.to_string()),
+            "P0" | "PO" => {
+                // The bases can be in any order
+                let order = value
+                    .split_whitespace()
+                    .map(|base| {
+                        ["A", "C", "G", "T"]
+                            .iter()
+                            .position(|b| b.eq_ignore_ascii_case(base))
+                            .ok_or_else(|| parse_error(format!("unknown base {:?}", base)))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let mut sorted = order.clone();
+                sorted.sort_unstable();
+                if sorted != [0, 1, 2, 3] {
+                    return Err(parse_error("expected the bases A, C, G and T".to_string()));
+                }
+                motif.order = Some(order);
+            }
+            key if key.chars().all(|c| c.is_ascii_digit()) && !key.is_empty() => {
+                let order = motif
+                    .order
+                    .as_ref()
+                    .ok_or_else(|| parse_error("counts before the P0 line".to_string()))?;
+                let counts = value
+                    .split_whitespace()
+                    .take(4)
+                    .map(|count| {
+                        count
+                            .parse::<f64>()
+                            .map_err(|e| parse_error(format!("invalid count {:?}: {}", count, e)))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                if counts.len() != 4 {
+                    return Err(parse_error(format!(
+                        "expected 4 counts, got {}",
+                        counts.len()
+                    )));
+                }
+
+                let mut row = [0.0; 4];
+                for (&base, count) in order.iter().zip(counts) {
+                    row[base] = count;
+                }
+                check_row(&row).map_err(parse_error)?;
+                motif.rows.push(row);
+            }
+            _ => {}
+        }
+    }
+
+    // The last matrix may miss its terminating line
+    motif.finish(&mut motifs);
+    Ok(motifs)
+}
+
+/// A motif while its lines are read.
+#[derive(Default)]
+struct PartialMotif {
+    id: Option<String>,
+    accession: Option<String>,
+    /// Base index of each count column
+    order: Option<Vec<usize>>,
+    rows: Vec<[f64; 4]>,
+}
+
+impl PartialMotif {
+    /// Adds the motif read so far, if it has a matrix, and starts the next one.
+    fn finish(&mut self, motifs: &mut Vec<Motif>) {
+        let motif = std::mem::take(self);
+        if motif.rows.is_empty() {
+            return;
+        }
+
+        motifs.push(Motif {
+            name: motif
+                .id
+                .or(motif.accession)
+                .unwrap_or_else(|| format!("motif_{}", motifs.len() + 1)),
+            pfm: from_weights(&motif.rows),
+            log_e_value: None,
+        });
+    }
+}
+
+/// Writes `motifs` so [`read_transfac`] and other TRANSFAC readers read them back, with the
+/// consensus base at the end of each row.
+pub fn write_transfac(mut writer: impl Write, motifs: &[Motif]) -> Result<()> {
+    for motif in motifs {
+        writeln!(writer, "AC  {}", motif.name)?;
+        writeln!(writer, "XX")?;
+        writeln!(writer, "ID  {}", motif.name)?;
+        writeln!(writer, "XX")?;
+        writeln!(writer, "P0      A      C      G      T")?;
+        let consensus = motif.pfm.get_consensus_string();
+        for (i, (row, base)) in motif.pfm.matrix.iter().zip(consensus.chars()).enumerate() {
+            writeln!(
+                writer,
+                "{:02}  {:>5}  {:>5}  {:>5}  {:>5}      {}",
+                i + 1,
+                row[0],
+                row[1],
+                row[2],
+                row[3],
+                base
+            )?;
+        }
+        writeln!(writer, "XX")?;
+        writeln!(writer, "//")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datastructures::{pfm::Pfm, sequence::Sequence};
+
+    #[test]
+    fn test_read_write_transfac() {
+        let motifs = vec![
+            Motif {
+                name: "first".to_string(),
+                pfm: Pfm::from_sequences(&[Sequence::from("GATA"), Sequence::from("GATT")]),
+                log_e_value: None,
+            },
+            Motif {
+                name: "second".to_string(),
+                pfm: Pfm::from_sequences(&[Sequence::from("CCG")]),
+                log_e_value: None,
+            },
+        ];
+
+        let mut written = Vec::new();
+        write_transfac(&mut written, &motifs).unwrap();
+
+        assert_eq!(read_transfac(written.as_slice()).unwrap(), motifs);
+    }
+
+    #[test]
+    fn test_read_transfac_example() {
+        let input = "AC  M00001\nXX\nDE  MyoD\nXX\nPO  T  G  C  A\n01  0  1  2  2  S\n\
+            02  1.0  2.0  0.0  2.0  N\nXX\n//\nAC  M00002\nP0 A C G T\n01 5 0 0 0 A\n";
+
+        let motifs = read_transfac(input.as_bytes()).unwrap();
+
+        assert_eq!(motifs.len(), 2);
+        assert_eq!(motifs[0].name, "M00001");
+        assert_eq!(motifs[0].pfm.matrix, vec![[2, 2, 1, 0], [2, 0, 2, 1]]);
+        assert_eq!(motifs[1].pfm.get_consensus_string(), "A");
+    }
+
+    #[test]
+    fn test_read_transfac_frequencies() {
+        let input = "P0 A C G T\n01 0.25 0.25 0.25 0.25\n02 0.05 0.9 0.05 0\n";
+
+        let motifs = read_transfac(input.as_bytes()).unwrap();
+
+        assert_eq!(motifs[0].pfm.matrix, vec![[5, 5, 5, 5], [1, 18, 1, 0]]);
+
+        let err = read_transfac("P0 A C G T\n01 1 0 0 0\n02 0 0 0 0\n".as_bytes()).unwrap_err();
+        assert!(matches!(err, Error::Parse { line: 3, .. }), "{:?}", err);
+    }
+
+    #[test]
+    fn test_read_transfac_counts_before_p0() {
+        let err = read_transfac("ID  a\n01  1 0 0 0\n".as_bytes()).unwrap_err();
+
+        assert!(matches!(err, Error::Parse { line: 2, .. }), "{:?}", err);
+    }
+}
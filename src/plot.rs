@@ -1,9 +1,11 @@
+//! Sequence logos of found motifs.
+
 use full_palette::ORANGE;
 use image::DynamicImage;
 use itertools::Itertools;
 use plotters::{prelude::*, style::text_anchor::Pos};
 
-use crate::datastructures::pwm::Pwm;
+use crate::{datastructures::pwm::Pwm, Error, Result};
 
 /// Nucleotides and their colors
 const BASES: [&str; 4] = ["A", "C", "G", "T"];
@@ -11,66 +13,80 @@ const COLORS: [RGBColor; 4] = [RED, BLUE, GREEN, ORANGE];
 
 const CACHE_DIR: &str = ".cache";
 
-fn create_and_load_char_bitmaps() -> [DynamicImage; 4] {
+impl<E: std::error::Error + Send + Sync> From<DrawingAreaErrorKind<E>> for Error {
+    fn from(e: DrawingAreaErrorKind<E>) -> Self {
+        Error::Plot(e.to_string())
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Self {
+        Error::Plot(e.to_string())
+    }
+}
+
+fn create_and_load_char_bitmaps() -> Result<[DynamicImage; 4]> {
     let mut images = Vec::new();
     for base in BASES {
         let file_path = format!("{}/{}.bmp", CACHE_DIR, base);
         if let Ok(true) = std::fs::exists(file_path.clone()) {
-            images.push(image::open(&file_path).unwrap());
+            images.push(image::open(&file_path)?);
             continue;
         }
 
         if let Ok(false) = std::fs::exists(CACHE_DIR) {
-            std::fs::create_dir(CACHE_DIR).unwrap();
+            std::fs::create_dir(CACHE_DIR)?;
         }
         let size_mul = 2;
 
         let root =
             BitMapBackend::new(&file_path, (75 * size_mul, 100 * size_mul)).into_drawing_area();
-        root.fill(&WHITE).unwrap();
+        root.fill(&WHITE)?;
 
         let mut chart = ChartBuilder::on(&root)
             .margin(0)
-            .build_cartesian_2d(0.0..1.0, 0.0..1.0)
-            .unwrap();
-
-        chart
-            .draw_series(std::iter::once(Text::new(
-                base,
-                (0.5, 0.4),
-                ("FiraCode Nerd Font Mono", 165 * size_mul)
-                    .into_font()
-                    .color(&COLORS[BASES.iter().position(|&x| x == base).unwrap()])
-                    .pos(Pos::new(
-                        plotters::style::text_anchor::HPos::Center,
-                        plotters::style::text_anchor::VPos::Center,
-                    )),
-            )))
-            .unwrap();
+            .build_cartesian_2d(0.0..1.0, 0.0..1.0)?;
+
+        chart.draw_series(std::iter::once(Text::new(
+            base,
+            (0.5, 0.4),
+            ("FiraCode Nerd Font Mono", 165 * size_mul)
+                .into_font()
+                .color(&COLORS[BASES.iter().position(|&x| x == base).unwrap()])
+                .pos(Pos::new(
+                    plotters::style::text_anchor::HPos::Center,
+                    plotters::style::text_anchor::VPos::Center,
+                )),
+        )))?;
 
         chart
             .configure_mesh()
             .disable_mesh()
             .disable_axes()
-            .draw()
-            .unwrap();
+            .draw()?;
 
-        root.present().unwrap();
+        root.present()?;
 
-        images.push(image::open(&file_path).unwrap());
+        images.push(image::open(&file_path)?);
     }
 
-    images.try_into().unwrap()
+    Ok(images
+        .try_into()
+        .unwrap_or_else(|_| unreachable!("one image is loaded for each base")))
 }
 
-pub fn clear_cache() {
-    if let Ok(true) = std::fs::exists(CACHE_DIR) {
-        std::fs::remove_dir_all(CACHE_DIR).unwrap();
+/// Removes the cached base images used to draw logos.
+pub fn clear_cache() -> Result<()> {
+    if std::fs::exists(CACHE_DIR)? {
+        std::fs::remove_dir_all(CACHE_DIR)?;
     }
+
+    Ok(())
 }
 
-pub fn plot_pwm(name: &str, pwm: &Pwm, score: f64) -> Result<(), Box<dyn std::error::Error>> {
-    let images = create_and_load_char_bitmaps();
+/// Draws a sequence logo of the probability matrix `pwm` to the png file `name`.
+pub fn plot_pwm(name: &str, pwm: &Pwm, score: f64) -> Result<()> {
+    let images = create_and_load_char_bitmaps()?;
     let size_mul = 1;
     let root = BitMapBackend::new(name, (200 * pwm.len() as u32 * size_mul, 400 * size_mul))
         .into_drawing_area();
@@ -111,7 +127,7 @@ pub fn plot_pwm(name: &str, pwm: &Pwm, score: f64) -> Result<(), Box<dyn std::er
                 (w / pwm.len() as u32, (h as f64 * freq) as u32),
                 image.into_bytes(),
             )
-            .unwrap();
+            .ok_or_else(|| Error::Plot(format!("could not draw base {}", BASES[j])))?;
 
             chart.plotting_area().draw(&elem)?;
 
@@ -120,6 +136,5 @@ pub fn plot_pwm(name: &str, pwm: &Pwm, score: f64) -> Result<(), Box<dyn std::er
     }
 
     root.present()?;
-    println!("Sequence logo saved as {}", name);
     Ok(())
 }
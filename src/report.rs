@@ -0,0 +1,197 @@
+use itertools::Itertools;
+
+use de_novo_motif_finder::{
+    datastructures::{
+        background::Background, pwm::Pwm, score_distribution::ScoreDistribution, sequence::Strand,
+    },
+    fasta_reader::FastaRecord,
+    motif_finder::MotifResult,
+    plot,
+    scanner::ScanHit,
+    stats, Result,
+};
+
+/// Record index and segment index of each searched sequence.
+pub type Origins = [(usize, usize)];
+
+pub fn print_input_summary(
+    input: &str,
+    records: &[FastaRecord],
+    origins: &Origins,
+    background: &Background,
+) {
+    println!("Input file: {:?}", input);
+    println!("Sequences: {}", records.len());
+    println!(
+        "Average sequence length: {:.2}",
+        records.iter().map(|x| x.length).sum::<usize>() as f64 / records.len() as f64
+    );
+    println!(
+        "Masked positions: {}",
+        records.iter().map(|x| x.masked()).sum::<usize>()
+    );
+    println!("Searched segments: {}", origins.len());
+    println!(
+        "Background (A C G T): {}",
+        background
+            .frequencies
+            .iter()
+            .map(|f| format!("{:.3}", f))
+            .join(" ")
+    );
+    if background.order() > 0 {
+        println!("Background Markov order: {}", background.order());
+    }
+    for record in records.iter().filter(|x| x.dropped > 0) {
+        println!(
+            "Dropped {} invalid characters from {}",
+            record.dropped, record.id
+        );
+    }
+    println!("====================");
+}
+
+/// Prints the found motifs, with the sites given as 1-indexed, inclusive coordinates in the
+/// records they were found in, followed by the strand, the site read along that strand and its
+/// p-value, which is NA for Markov backgrounds as the scanner has it.
+pub fn print_results(
+    results: &[MotifResult],
+    records: &[FastaRecord],
+    origins: &Origins,
+    background: &Background,
+    plot_logos: bool,
+) -> Result<()> {
+    for result in results {
+        let pwm = Pwm::log_odds(&result.pfm, background);
+        let distribution =
+            (background.order() == 0).then(|| ScoreDistribution::new(&pwm, background));
+
+        println!("{:?}", result.consensus);
+        println!("Score: {:.2}", result.score);
+        println!(
+            "p-value: {}",
+            stats::ln_to_scientific(result.log_p_value, 2)
+        );
+        println!(
+            "E-value: {}",
+            stats::ln_to_scientific(result.log_e_value, 2)
+        );
+        println!(
+            "Information content: {:.2} bits",
+            result.information_content
+        );
+        // Records split into segments count once, with a site in any of their segments
+        let records_with_site = result
+            .sites
+            .iter()
+            .map(|site| origins[site.sequence].0)
+            .unique()
+            .count();
+        let searched_records = origins.iter().map(|(record, _)| record).dedup().count();
+        println!(
+            "Site coverage: {}/{} records ({:.1}%)",
+            records_with_site,
+            searched_records,
+            100.0 * records_with_site as f64 / searched_records as f64
+        );
+        if let Some(log_likelihood) = result.log_likelihood {
+            println!("EM log-likelihood ratio: {:.2}", log_likelihood);
+        }
+        if let Some(enrichment) = result.enrichment {
+            println!(
+                "Sequences with a site: {}/{}, controls: {}/{}",
+                enrichment.positives,
+                enrichment.positive_total,
+                enrichment.controls,
+                enrichment.control_total
+            );
+            println!("Enrichment p-value: {:.3e}", enrichment.p_value);
+        }
+        println!("Sites:");
+        for site in result.sites.iter() {
+            let (record, segment) = origins[site.sequence];
+            let record = &records[record];
+            let segment = &record.segments[segment];
+            let end = site.start + result.pfm.len();
+            let matched = segment.sequence.slice(site.start..end);
+            let matched = match site.strand {
+                Strand::Forward => matched,
+                Strand::Reverse => matched.reverse_complement(),
+            };
+            println!(
+                "  {}\t{}\t{}\t{}\t{}\t{}",
+                record.id,
+                segment.offset + site.start + 1,
+                segment.offset + end,
+                site.strand,
+                matched
+                    .bases
+                    .iter()
+                    .map(|&base| char::from(base))
+                    .collect::<String>(),
+                distribution
+                    .as_ref()
+                    .map_or("NA".to_string(), |distribution| format!(
+                        "{:.3e}",
+                        distribution.window_p_value(&pwm, &matched, 0)
+                    ))
+            );
+        }
+
+        if plot_logos {
+            let pwm = Pwm::pfm_into_ppm(result.pfm.clone());
+            let name = format!("{}.png", result.consensus);
+            plot::plot_pwm(&name, &pwm, result.score)?;
+            println!("Sequence logo saved as {}", name);
+        }
+        println!();
+    }
+
+    if plot_logos {
+        plot::clear_cache()?;
+    }
+
+    Ok(())
+}
+
+pub fn print_scan_header() {
+    println!("motif_id\tsequence_name\tstart\tstop\tstrand\tscore\tp-value\tmatched_sequence");
+}
+
+/// Prints one line for each hit, with 1-based inclusive coordinates in the record. Hits without
+/// a p-value get NA.
+pub fn print_scan_hits(
+    motif: &str,
+    hits: &[ScanHit],
+    width: usize,
+    records: &[FastaRecord],
+    origins: &Origins,
+) {
+    for hit in hits {
+        let (record, segment) = origins[hit.sequence];
+        let record = &records[record];
+        let segment = &record.segments[segment];
+        let matched = segment.sequence.slice(hit.start..hit.start + width);
+        let matched = match hit.strand {
+            Strand::Forward => matched,
+            Strand::Reverse => matched.reverse_complement(),
+        };
+
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{:.3}\t{}\t{}",
+            motif,
+            record.id,
+            segment.offset + hit.start + 1,
+            segment.offset + hit.start + width,
+            hit.strand,
+            hit.score,
+            hit.p_value
+                .map_or("NA".to_string(), |p_value| format!("{:.3e}", p_value)),
+            matched
+                .bases
+                .iter()
+                .map(|&base| char::from(base))
+                .collect::<String>()
+        );
+    }
+}
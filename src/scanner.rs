@@ -0,0 +1,184 @@
+//! Scanning sequences for sites of a known motif.
+
+use crate::{
+    datastructures::{
+        background::Background,
+        pfm::Pfm,
+        pwm::Pwm,
+        score_distribution::ScoreDistribution,
+        sequence::{Sequence, Strand},
+    },
+    Error, Result,
+};
+
+/// Which windows are reported as sites.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScanThreshold {
+    /// Windows scoring at least this
+    Score(f64),
+    /// Windows with at most this p-value
+    PValue(f64),
+}
+
+/// A window of one of the scanned sequences that matches the motif.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScanHit {
+    /// Index of the sequence in the input given to the scan
+    pub sequence: usize,
+    /// Position of the first base of the site on the forward strand
+    pub start: usize,
+    pub strand: Strand,
+    /// Log2-odds score of the window against the background
+    pub score: f64,
+    /// Probability of a random window from the background scoring at least as high, see
+    /// [`ScoreDistribution`]. Only known for backgrounds of order 0.
+    pub p_value: Option<f64>,
+}
+
+/// Scores every window of `seqs` on both strands with the log2-odds scores of `pfm` against
+/// `background`, and returns the windows passing `threshold` in the order they appear in.
+///
+/// The score distribution only models the base composition, so with a Markov background the
+/// hits have no p-value and a p-value threshold is an error.
+pub fn scan(
+    pfm: &Pfm,
+    seqs: &[Sequence],
+    background: &Background,
+    threshold: ScanThreshold,
+) -> Result<Vec<ScanHit>> {
+    let markov = background.order() > 0;
+    if markov && matches!(threshold, ScanThreshold::PValue(_)) {
+        return Err(Error::InvalidConfig(
+            "p-value thresholds need a background of order 0, use a score threshold".to_string(),
+        ));
+    }
+
+    // The reverse strand is scanned with the matrix of the reverse complement, scored against
+    // the same background as it is read on the forward strand
+    let strands = [
+        (Strand::Forward, pfm.clone()),
+        (Strand::Reverse, pfm.reverse_complement()),
+    ]
+    .map(|(strand, pfm)| {
+        let pwm = Pwm::log_odds(&pfm, background);
+        let distribution = (!markov).then(|| ScoreDistribution::new(&pwm, background));
+        (strand, pwm, distribution)
+    });
+
+    let mut hits = Vec::new();
+    for (i, seq) in seqs.iter().enumerate() {
+        for start in 0..(seq.len() + 1).saturating_sub(pfm.len()) {
+            for (strand, pwm, distribution) in strands.iter() {
+                let score = pwm.score_window(seq, start, background);
+                let p_value = distribution
+                    .as_ref()
+                    .map(|distribution| distribution.window_p_value(pwm, seq, start));
+                let passes = match threshold {
+                    ScanThreshold::Score(min_score) => score >= min_score,
+                    ScanThreshold::PValue(max_p_value) => p_value.unwrap() <= max_p_value,
+                };
+
+                if passes {
+                    hits.push(ScanHit {
+                        sequence: i,
+                        start,
+                        strand: *strand,
+                        score,
+                        p_value,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_both_strands() {
+        let pfm = Pfm::from_sequences(&[Sequence::from("GATTAC")]);
+        let pwm = Pwm::log_odds(&pfm, &Background::uniform());
+        let seqs = [
+            Sequence::from("CCGATTACCC"),
+            Sequence::from("CCCCCCCCCC"),
+            Sequence::from("AGTAATCCAA"),
+        ];
+
+        let hits = scan(
+            &pfm,
+            &seqs,
+            &Background::uniform(),
+            ScanThreshold::Score(pwm.max_score()),
+        )
+        .unwrap();
+
+        assert_eq!(hits.len(), 2);
+        assert_eq!((hits[0].sequence, hits[0].start), (0, 2));
+        assert_eq!(hits[0].strand, Strand::Forward);
+        assert_eq!((hits[1].sequence, hits[1].start), (2, 1));
+        assert_eq!(hits[1].strand, Strand::Reverse);
+    }
+
+    #[test]
+    fn test_scan_p_value_threshold() {
+        let pfm = Pfm::from_sequences(&[Sequence::from("GATTACA")]);
+        let pwm = Pwm::log_odds(&pfm, &Background::uniform());
+        let seqs = [Sequence::from("TTTTGATTACATTTTT")];
+
+        let hits = scan(
+            &pfm,
+            &seqs,
+            &Background::uniform(),
+            ScanThreshold::PValue(1e-3),
+        )
+        .unwrap();
+
+        // A single mismatch is already rarer than this, and only one window matches
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].start, 4);
+        assert_eq!(hits[0].p_value, Some(0.25f64.powi(7)));
+
+        let all = scan(
+            &pfm,
+            &seqs,
+            &Background::uniform(),
+            ScanThreshold::PValue(1.0),
+        )
+        .unwrap();
+        assert_eq!(all.len(), 2 * (seqs[0].len() - pwm.len() + 1));
+    }
+
+    #[test]
+    fn test_scan_reverse_strand_asymmetric_background() {
+        let pfm = Pfm::from_sequences(&[Sequence::from("GATTAC")]);
+        let background = Background::from_frequencies([0.5, 0.25, 0.125, 0.125]).unwrap();
+        let seqs = [Sequence::from("GTAATC")];
+
+        let hits = scan(&pfm, &seqs, &background, ScanThreshold::PValue(1e-3)).unwrap();
+
+        // The site is scored against the background of the bases it has on the forward strand
+        let expected = -[0.125, 0.125, 0.5, 0.5, 0.125, 0.25]
+            .iter()
+            .map(|p: &f64| p.log2())
+            .sum::<f64>();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].strand, Strand::Reverse);
+        assert!((hits[0].score - expected).abs() < 1e-9, "{:?}", hits);
+        assert!((hits[0].p_value.unwrap() - 0.125f64.powi(3) * 0.5 * 0.5 * 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_scan_markov_background_p_value_threshold() {
+        let pfm = Pfm::from_sequences(&[Sequence::from("GATTAC")]);
+        let background = Background::markov_from_sequences(&[Sequence::from("CGCGATAT")], 1);
+        let seqs = [Sequence::from("CCGATTACCC")];
+
+        assert!(scan(&pfm, &seqs, &background, ScanThreshold::PValue(1e-3)).is_err());
+        let hits = scan(&pfm, &seqs, &background, ScanThreshold::Score(0.0)).unwrap();
+        assert!(hits.iter().all(|hit| hit.p_value.is_none()));
+    }
+}
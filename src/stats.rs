@@ -0,0 +1,285 @@
+//! Statistics used to judge how significant a motif is.
+
+/// Natural log of the gamma function, using the Lanczos approximation.
+pub fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection formula
+        return (std::f64::consts::PI / (std::f64::consts::PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, c)| {
+            sum + c / (x + i as f64 + 1.0)
+        });
+
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// Natural log of the binomial coefficient `n` over `k`.
+pub fn ln_choose(n: usize, k: usize) -> f64 {
+    if k > n {
+        return f64::NEG_INFINITY;
+    }
+
+    ln_gamma(n as f64 + 1.0) - ln_gamma(k as f64 + 1.0) - ln_gamma((n - k) as f64 + 1.0)
+}
+
+/// Probability of drawing at least `k` successes in `draws` draws without replacement from a
+/// population of `population` holding `successes` successes.
+pub fn hypergeometric_upper_tail(
+    k: usize,
+    population: usize,
+    successes: usize,
+    draws: usize,
+) -> f64 {
+    let min = draws.saturating_sub(population - successes);
+    let max = successes.min(draws);
+    if k <= min {
+        return 1.0;
+    }
+    if k > max {
+        return 0.0;
+    }
+
+    let ln_total = ln_choose(population, draws);
+    let p = (k..=max)
+        .map(|i| {
+            (ln_choose(successes, i) + ln_choose(population - successes, draws - i) - ln_total)
+                .exp()
+        })
+        .sum::<f64>();
+
+    p.min(1.0)
+}
+
+/// Probability of at least `k` successes in `n` independent trials that each succeed with
+/// probability `p`.
+pub fn binomial_upper_tail(k: usize, n: usize, p: f64) -> f64 {
+    if k == 0 {
+        return 1.0;
+    }
+    if k > n || p <= 0.0 {
+        return 0.0;
+    }
+    if p >= 1.0 {
+        return 1.0;
+    }
+
+    let (ln_p, ln_q) = (p.ln(), (-p).ln_1p());
+    let tail = (k..=n)
+        .map(|i| (ln_choose(n, i) + i as f64 * ln_p + (n - i) as f64 * ln_q).exp())
+        .sum::<f64>();
+
+    tail.min(1.0)
+}
+
+/// One sided Fisher exact test p-value for `positives` out of `positive_total` sequences having a
+/// site being more than the `controls` out of `control_total` control sequences.
+pub fn fisher_enrichment(
+    positives: usize,
+    positive_total: usize,
+    controls: usize,
+    control_total: usize,
+) -> f64 {
+    hypergeometric_upper_tail(
+        positives,
+        positive_total + control_total,
+        positives + controls,
+        positive_total,
+    )
+}
+
+/// Natural log of the upper regularized incomplete gamma function Q(a, x), computed in log space
+/// so tiny p-values do not round to zero.
+pub fn ln_upper_regularized_gamma(a: f64, x: f64) -> f64 {
+    const EPSILON: f64 = 1e-15;
+    const MAX_ITERATIONS: usize = 1000;
+
+    if x <= 0.0 {
+        return 0.0;
+    }
+
+    let ln_prefix = a * x.ln() - x - ln_gamma(a);
+
+    if x < a + 1.0 {
+        // Series of the lower function P(a, x), Q = 1 - P
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..MAX_ITERATIONS {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term < sum * EPSILON {
+                break;
+            }
+        }
+
+        return (-(ln_prefix + sum.ln()).exp()).ln_1p();
+    }
+
+    // Continued fraction of Q(a, x), evaluated with the modified Lentz method
+    let tiny = 1e-300;
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / tiny;
+    let mut d = 1.0 / b;
+    let mut fraction = d;
+    for i in 1..MAX_ITERATIONS {
+        let an = -(i as f64) * (i as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < tiny {
+            d = tiny;
+        }
+        c = b + an / c;
+        if c.abs() < tiny {
+            c = tiny;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        fraction *= delta;
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+
+    ln_prefix + fraction.ln()
+}
+
+/// Natural log of the probability of a chi-square distributed variable with `degrees_of_freedom`
+/// being at least `x`.
+pub fn ln_chi_square_upper_tail(x: f64, degrees_of_freedom: usize) -> f64 {
+    ln_upper_regularized_gamma(degrees_of_freedom as f64 / 2.0, x / 2.0)
+}
+
+/// Scientific notation of a value given as its natural log, with `decimals` digits after the
+/// point. Works for values too small to be represented as a float.
+pub fn ln_to_scientific(ln: f64, decimals: usize) -> String {
+    if ln == f64::NEG_INFINITY {
+        return format!("{:.*}e0", decimals, 0.0);
+    }
+
+    let log10 = ln / std::f64::consts::LN_10;
+    let mut exponent = log10.floor();
+    let scale = 10f64.powi(decimals as i32);
+    let mut mantissa = (10f64.powf(log10 - exponent) * scale).round() / scale;
+    if mantissa >= 10.0 {
+        mantissa /= 10.0;
+        exponent += 1.0;
+    }
+
+    format!("{:.*}e{}", decimals, mantissa, exponent)
+}
+
+/// Natural log of a number in decimal or scientific notation, such as `4.05e-541`, that may be
+/// too small or large to be represented as a float.
+pub fn ln_from_scientific(value: &str) -> Option<f64> {
+    let (mantissa, exponent) = match value.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+        None => (value, 0),
+    };
+    let mantissa = mantissa.parse::<f64>().ok()?;
+    if mantissa < 0.0 {
+        return None;
+    }
+
+    Some(mantissa.ln() + exponent as f64 * std::f64::consts::LN_10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9 * b.abs().max(1.0), "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_ln_scientific() {
+        assert_eq!(ln_to_scientific(1.5e-3f64.ln(), 2), "1.50e-3");
+        assert_eq!(ln_to_scientific(0.0, 1), "1.0e0");
+        // Rounds up to the next power of ten instead of giving a mantissa of 10
+        assert_eq!(ln_to_scientific(9.99e-5f64.ln(), 1), "1.0e-4");
+        assert_eq!(ln_to_scientific(-2000.0, 2), "2.58e-869");
+
+        assert_close(
+            ln_from_scientific("4.05e-541").unwrap(),
+            4.05f64.ln() - 541.0 * std::f64::consts::LN_10,
+        );
+        assert_close(ln_from_scientific("1.5e-3").unwrap(), 1.5e-3f64.ln());
+        assert_close(ln_from_scientific("0.25").unwrap(), 0.25f64.ln());
+        assert_eq!(ln_from_scientific("E-3"), None);
+    }
+
+    #[test]
+    fn test_ln_gamma() {
+        assert_close(ln_gamma(1.0), 0.0);
+        assert_close(ln_gamma(0.5), 0.572_364_942_924_700_4);
+        assert_close(ln_gamma(10.3), 13.482_036_786_138_36);
+    }
+
+    #[test]
+    fn test_ln_chi_square_upper_tail() {
+        // Both branches of the incomplete gamma function
+        assert_close(ln_chi_square_upper_tail(1.0, 2).exp(), (-0.5f64).exp());
+        assert_close(ln_chi_square_upper_tail(10.0, 2), -5.0);
+        assert_close(
+            ln_chi_square_upper_tail(3.0, 4).exp(),
+            0.557_825_400_371_075_2,
+        );
+        assert_close(
+            ln_chi_square_upper_tail(20.0, 4).exp(),
+            11.0 * (-10.0f64).exp(),
+        );
+        // Far too small to represent outside of log space
+        assert_close(ln_chi_square_upper_tail(2000.0, 2), -1000.0);
+        assert_eq!(ln_chi_square_upper_tail(0.0, 3), 0.0);
+    }
+
+    #[test]
+    fn test_hypergeometric_upper_tail() {
+        assert_close(
+            hypergeometric_upper_tail(8, 20, 10, 10),
+            0.011_507_068_782_610_578,
+        );
+        assert_close(
+            hypergeometric_upper_tail(3, 10, 4, 5),
+            0.261_904_761_904_761_9,
+        );
+        assert_eq!(hypergeometric_upper_tail(0, 20, 10, 10), 1.0);
+        assert_eq!(hypergeometric_upper_tail(11, 20, 10, 10), 0.0);
+    }
+
+    #[test]
+    fn test_binomial_upper_tail() {
+        assert_close(binomial_upper_tail(8, 10, 0.5), 56.0 / 1024.0);
+        assert_close(binomial_upper_tail(2, 3, 0.1), 0.028);
+        assert_eq!(binomial_upper_tail(0, 10, 0.5), 1.0);
+        assert_eq!(binomial_upper_tail(11, 10, 0.5), 0.0);
+        assert_eq!(binomial_upper_tail(1, 10, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_fisher_enrichment() {
+        // 10 of 20 sequences have a site, none of the 20 controls
+        assert_close(
+            fisher_enrichment(10, 20, 0, 20),
+            0.000_217_959_895_379_250_23,
+        );
+        assert!(fisher_enrichment(5, 20, 5, 20) > 0.5);
+    }
+}
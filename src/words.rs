@@ -0,0 +1,596 @@
+//! Word based motif discovery. Every k-mer of the input, optionally with a gap of wildcards in
+//! its middle, is counted in the sequences with up to a number of mismatches, and the words most
+//! enriched over a background or control set are reported as motifs made of their occurrences.
+
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::{
+    datastructures::{
+        background::Background,
+        base::Base,
+        pfm::Pfm,
+        sequence::{Sequence, Strand},
+    },
+    motif_finder::{result_from_sites, MotifResult, SearchConfig, Site},
+    stats, Error, Result,
+};
+
+/// Longest word that fits the 2 bits per base encoding
+const MAX_WORD_LENGTH: usize = 32;
+
+/// Most words within the mismatches of a word that are enumerated for it, which allows 3
+/// mismatches for words of up to 13 bases and 2 for words of any length
+const MAX_NEIGHBORS: usize = 10_000;
+
+/// Character written for the wildcards of a gapped word
+const WILDCARD: char = 'N';
+
+/// What the occurrence of a word in the input is compared to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WordControl {
+    /// The occurrence expected from the base composition of the background
+    Background,
+    /// The occurrence in the input with the bases of each sequence shuffled
+    Shuffled { seed: u64 },
+    /// The occurrence in these control sequences
+    Sequences(Vec<Sequence>),
+}
+
+/// Tunables for the word search, the words have the min length of the [`SearchConfig`] besides
+/// their gap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordConfig {
+    /// Longest gap of wildcards in the middle of a word, 0 only counts ungapped words
+    pub max_gap: usize,
+    /// Mismatches a window may have to the word and still count as an occurrence
+    pub mismatches: usize,
+    pub control: WordControl,
+}
+
+impl Default for WordConfig {
+    fn default() -> Self {
+        Self {
+            max_gap: 0,
+            mismatches: 1,
+            control: WordControl::Background,
+        }
+    }
+}
+
+/// A word enriched in the input.
+#[derive(Debug, Clone)]
+pub struct Word {
+    /// The bases of the word, with [`WILDCARD`]s in its gap
+    pub word: String,
+    /// Amount of input sequences with an occurrence
+    pub positives: usize,
+    pub positive_total: usize,
+    /// Amount of input sequences expected to have an occurrence from the control
+    pub expected: f64,
+    /// One sided p-value of the word occurring in more sequences than expected
+    pub p_value: f64,
+    /// The best occurrence in each sequence that has one
+    pub sites: Vec<Site>,
+    /// Counts of the bases of those occurrences, gap included, to seed other searches with
+    pub pfm: Pfm,
+}
+
+/// Where the bases of a gapped word are.
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    length: usize,
+    gap: usize,
+}
+
+impl Layout {
+    /// Bases before the gap
+    fn left(&self) -> usize {
+        self.length / 2
+    }
+
+    fn span(&self) -> usize {
+        self.length + self.gap
+    }
+
+    /// Offsets in a window of the bases of the word.
+    fn positions(&self) -> impl Iterator<Item = usize> {
+        let (left, gap, span) = (self.left(), self.gap, self.span());
+        (0..left).chain(left + gap..span)
+    }
+
+    fn encode(&self, seq: &Sequence, start: usize) -> u64 {
+        self.positions().enumerate().fold(0, |code, (i, offset)| {
+            code | (seq[start + offset].to_index() as u64) << (2 * i)
+        })
+    }
+
+    fn decode(&self, code: u64) -> String {
+        let mut word = vec![WILDCARD; self.span()];
+        for (i, offset) in self.positions().enumerate() {
+            let base = Base::try_from((code >> (2 * i) & 3) as usize).unwrap();
+            word[offset] = char::from(base);
+        }
+        word.into_iter().collect()
+    }
+
+    fn mismatches(&self, seq: &Sequence, start: usize, code: u64) -> usize {
+        self.positions()
+            .enumerate()
+            .filter(|&(i, offset)| seq[start + offset].to_index() as u64 != code >> (2 * i) & 3)
+            .count()
+    }
+}
+
+/// A counted word, before the words similar to more significant ones are left out.
+struct Candidate {
+    layout: Layout,
+    code: u64,
+    positives: usize,
+    /// Amount of input sequences with an exact occurrence
+    exact: usize,
+    expected: f64,
+    p_value: f64,
+}
+
+/// The sequences each word of one layout occurs in exactly.
+struct WordIndex {
+    sequences: HashMap<u64, Vec<usize>>,
+}
+
+impl WordIndex {
+    fn new(seqs: &[Vec<Sequence>], layout: Layout) -> Self {
+        let mut sequences: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, strands) in seqs.iter().enumerate() {
+            for seq in strands {
+                for start in 0..(seq.len() + 1).saturating_sub(layout.span()) {
+                    let list = sequences.entry(layout.encode(seq, start)).or_default();
+                    if list.last() != Some(&i) {
+                        list.push(i);
+                    }
+                }
+            }
+        }
+
+        Self { sequences }
+    }
+
+    /// Amount of sequences with a word within the mismatches of `code`.
+    fn count(&self, neighbors: &[u64], seen: &mut [usize], stamp: usize) -> usize {
+        let mut count = 0;
+        for sequences in neighbors.iter().filter_map(|code| self.sequences.get(code)) {
+            for &i in sequences {
+                if seen[i] != stamp {
+                    seen[i] = stamp;
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+}
+
+/// Searches `seqs` for the words of `config.min_length` bases that occur in more sequences than
+/// the control of `words` leads to expect, and returns at most `config.hits` of them, most
+/// significant first. Words within the allowed mismatches of a more significant word, or of its
+/// reverse complement when searching both strands, are left out.
+pub fn enriched_words(
+    seqs: &[Sequence],
+    config: &SearchConfig,
+    words: &WordConfig,
+) -> Result<Vec<Word>> {
+    config.validate()?;
+    let length = config.min_length;
+    if length > MAX_WORD_LENGTH {
+        return Err(Error::InvalidConfig(format!(
+            "words can be at most {} bases long, got {}",
+            MAX_WORD_LENGTH, length
+        )));
+    }
+    if words.mismatches >= length {
+        return Err(Error::InvalidConfig(format!(
+            "mismatches ({}) must be fewer than the word length ({})",
+            words.mismatches, length
+        )));
+    }
+    if neighbor_count(length, words.mismatches) > MAX_NEIGHBORS {
+        return Err(Error::InvalidConfig(format!(
+            "{} mismatches in words of {} bases give more than {} neighbours per word",
+            words.mismatches, length, MAX_NEIGHBORS
+        )));
+    }
+    if seqs.is_empty() {
+        return Err(Error::EmptyInput(
+            "no sequences to count words in".to_string(),
+        ));
+    }
+
+    let strands = |seqs: &[Sequence]| {
+        seqs.iter()
+            .map(|seq| {
+                let mut strands = vec![seq.clone()];
+                if config.both_strands {
+                    strands.push(seq.reverse_complement());
+                }
+                strands
+            })
+            .collect::<Vec<_>>()
+    };
+    let input = strands(seqs);
+    let controls = match &words.control {
+        WordControl::Background => None,
+        WordControl::Shuffled { seed } => {
+            let mut rng = StdRng::seed_from_u64(*seed);
+            let shuffled = seqs
+                .iter()
+                .map(|seq| {
+                    let mut bases = seq.bases.clone();
+                    bases.shuffle(&mut rng);
+                    Sequence { bases }
+                })
+                .collect::<Vec<_>>();
+            Some(strands(&shuffled))
+        }
+        WordControl::Sequences(controls) if controls.is_empty() => {
+            return Err(Error::EmptyInput(
+                "no control sequences to compare against".to_string(),
+            ))
+        }
+        WordControl::Sequences(controls) => Some(strands(controls)),
+    };
+
+    let mut candidates = Vec::new();
+    for gap in 0..=words.max_gap {
+        let layout = Layout { length, gap };
+        let index = WordIndex::new(&input, layout);
+        let control_index = controls
+            .as_ref()
+            .map(|controls| WordIndex::new(controls, layout));
+
+        let mut seen = vec![0; input.len()];
+        let mut control_seen = vec![0; controls.as_ref().map_or(0, Vec::len)];
+        for (stamp, &code) in index.sequences.keys().sorted().enumerate() {
+            let neighbors = neighbors(code, length, words.mismatches);
+            let positives = index.count(&neighbors, &mut seen, stamp + 1);
+            if positives < 2 {
+                continue;
+            }
+
+            let (expected, p_value) = match (&controls, &control_index) {
+                (Some(controls), Some(control_index)) => {
+                    let hits = control_index.count(&neighbors, &mut control_seen, stamp + 1);
+                    (
+                        hits as f64 * input.len() as f64 / controls.len() as f64,
+                        stats::fisher_enrichment(positives, input.len(), hits, controls.len()),
+                    )
+                }
+                _ => {
+                    let probability = occurrence_probability(
+                        &neighbors,
+                        length,
+                        &config.background,
+                        &input,
+                        layout,
+                    );
+                    (
+                        probability * input.len() as f64,
+                        stats::binomial_upper_tail(positives, input.len(), probability),
+                    )
+                }
+            };
+
+            candidates.push(Candidate {
+                layout,
+                code,
+                positives,
+                exact: index.sequences[&code].len(),
+                expected,
+                p_value,
+            });
+        }
+    }
+
+    // Of words occurring equally often, the one occurring exactly most often is the likeliest to
+    // be the motif and not one of its mismatches
+    let candidates = candidates.into_iter().sorted_by(|a, b| {
+        a.p_value
+            .partial_cmp(&b.p_value)
+            .unwrap()
+            .then(b.positives.cmp(&a.positives))
+            .then(b.exact.cmp(&a.exact))
+            .then(a.layout.gap.cmp(&b.layout.gap))
+            .then(a.code.cmp(&b.code))
+    });
+
+    let mut picked: Vec<Word> = Vec::new();
+    for Candidate {
+        layout,
+        code,
+        positives,
+        exact: _,
+        expected,
+        p_value,
+    } in candidates
+    {
+        if picked.len() >= config.hits {
+            break;
+        }
+
+        let word = layout.decode(code);
+        let similar = |other: &Word| {
+            let mut forms = vec![other.word.clone()];
+            if config.both_strands {
+                forms.push(reverse_complement(&other.word));
+            }
+            forms.iter().any(|form| {
+                form.len() == word.len()
+                    && form
+                        .chars()
+                        .zip(word.chars())
+                        .filter(|(a, b)| a != b)
+                        .count()
+                        <= words.mismatches
+            })
+        };
+        if picked.iter().any(similar) {
+            continue;
+        }
+
+        let sites = best_occurrences(&input, layout, code, words.mismatches);
+        let matched = sites
+            .iter()
+            .map(|site| {
+                let matched = seqs[site.sequence].slice(site.start..site.start + layout.span());
+                match site.strand {
+                    Strand::Forward => matched,
+                    Strand::Reverse => matched.reverse_complement(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        picked.push(Word {
+            word,
+            positives,
+            positive_total: input.len(),
+            expected,
+            p_value,
+            pfm: Pfm::from_sequences(&matched),
+            sites,
+        });
+    }
+
+    Ok(picked)
+}
+
+/// Searches `seqs` for the most enriched words of [`enriched_words`] and reports each as a motif
+/// made of the best occurrence of the word in each sequence, scored like the results of
+/// [`crate::motif_finder::motif_finder`]. The motifs are not searched further.
+pub fn word_motif_finder(
+    seqs: &[Sequence],
+    config: &SearchConfig,
+    words: &WordConfig,
+) -> Result<Vec<MotifResult>> {
+    let reverse_seqs = if config.both_strands {
+        seqs.iter().map(|seq| seq.reverse_complement()).collect()
+    } else {
+        Vec::new()
+    };
+
+    Ok(enriched_words(seqs, config, words)?
+        .into_iter()
+        .map(|word| result_from_sites(word.sites, word.pfm.len(), seqs, &reverse_seqs, config))
+        .collect())
+}
+
+/// Amount of words within `mismatches` of a word of `length` bases, itself included, saturating
+/// at `usize::MAX`.
+fn neighbor_count(length: usize, mismatches: usize) -> usize {
+    let mut choices = 1usize;
+    let mut count = 1usize;
+    for k in 1..=mismatches {
+        // Ways to pick k of the positions times 3 other bases for each
+        choices = choices.saturating_mul(length - k + 1) / k;
+        count = count.saturating_add(choices.saturating_mul(3usize.saturating_pow(k as u32)));
+    }
+
+    count
+}
+
+/// Every word within `mismatches` of `code`, itself included.
+fn neighbors(code: u64, length: usize, mismatches: usize) -> Vec<u64> {
+    let mut neighbors = vec![code];
+    let mut frontier = vec![(code, 0)];
+    for _ in 0..mismatches {
+        let mut next = Vec::new();
+        for (word, first) in frontier {
+            // Only change positions after the last changed one, so each word is made once
+            for i in first..length {
+                let original = code >> (2 * i) & 3;
+                for base in (0..4).filter(|&base| base != original) {
+                    let neighbor = word & !(3 << (2 * i)) | base << (2 * i);
+                    neighbors.push(neighbor);
+                    next.push((neighbor, i + 1));
+                }
+            }
+        }
+        frontier = next;
+    }
+
+    neighbors
+}
+
+/// Probability of a sequence of the input having a window matching one of `neighbors`, averaged
+/// over the sequences, from the base composition of `background`.
+fn occurrence_probability(
+    neighbors: &[u64],
+    length: usize,
+    background: &Background,
+    seqs: &[Vec<Sequence>],
+    layout: Layout,
+) -> f64 {
+    let window = neighbors
+        .iter()
+        .map(|code| {
+            (0..length)
+                .map(|i| background.frequencies[(code >> (2 * i) & 3) as usize])
+                .product::<f64>()
+        })
+        .sum::<f64>()
+        .min(1.0);
+
+    seqs.iter()
+        .map(|strands| {
+            let windows = strands
+                .iter()
+                .map(|seq| (seq.len() + 1).saturating_sub(layout.span()))
+                .sum::<usize>();
+            1.0 - (1.0 - window).powi(windows as i32)
+        })
+        .sum::<f64>()
+        / seqs.len() as f64
+}
+
+/// The window with the fewest mismatches to the word in each sequence that has one within
+/// `mismatches`, the first one on a tie.
+fn best_occurrences(
+    seqs: &[Vec<Sequence>],
+    layout: Layout,
+    code: u64,
+    mismatches: usize,
+) -> Vec<Site> {
+    let strands = [Strand::Forward, Strand::Reverse];
+
+    seqs.iter()
+        .enumerate()
+        .filter_map(|(i, seqs)| {
+            seqs.iter()
+                .zip(strands)
+                .flat_map(|(seq, strand)| {
+                    (0..(seq.len() + 1).saturating_sub(layout.span())).map(move |start| {
+                        let site = Site {
+                            sequence: i,
+                            start: match strand {
+                                Strand::Forward => start,
+                                Strand::Reverse => seq.len() - start - layout.span(),
+                            },
+                            strand,
+                        };
+                        (layout.mismatches(seq, start, code), site)
+                    })
+                })
+                .filter(|(count, _)| *count <= mismatches)
+                .min_by_key(|(count, _)| *count)
+                .map(|(_, site)| site)
+        })
+        .collect()
+}
+
+fn reverse_complement(word: &str) -> String {
+    word.chars()
+        .rev()
+        .map(|c| match c {
+            'A' => 'T',
+            'C' => 'G',
+            'G' => 'C',
+            'T' => 'A',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seqs() -> Vec<Sequence> {
+        [
+            "TTGACAGATTACAGCTTAGCAT",
+            "CCATGATTACAGTTGCAGCA",
+            "ATGATAACAGGGCATCGTAC",
+            "GCTAGCTAGATTACAGTCCA",
+            "CACGTTCCAGGTCAGTCAAT",
+        ]
+        .into_iter()
+        .map(Sequence::from)
+        .collect()
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let neighbors = neighbors(0, 4, 2);
+
+        // 1 + 4 * 3 + 6 * 9
+        assert_eq!(neighbors.len(), 67);
+        assert_eq!(neighbors.iter().unique().count(), 67);
+        assert_eq!(neighbor_count(4, 2), 67);
+    }
+
+    #[test]
+    fn test_too_many_mismatches() {
+        let config = SearchConfig {
+            min_length: 14,
+            ..Default::default()
+        };
+        let mut words = WordConfig {
+            mismatches: 3,
+            ..Default::default()
+        };
+        assert!(matches!(
+            enriched_words(&seqs(), &config, &words),
+            Err(Error::InvalidConfig(_))
+        ));
+
+        words.mismatches = 2;
+        assert!(enriched_words(&seqs(), &config, &words).is_ok());
+    }
+
+    #[test]
+    fn test_enriched_words_with_mismatch() {
+        let config = SearchConfig {
+            hits: 1,
+            min_length: 7,
+            ..Default::default()
+        };
+
+        let words = enriched_words(&seqs(), &config, &WordConfig::default()).unwrap();
+
+        // GATAACA is one mismatch from the word
+        assert_eq!(words[0].word, "GATTACA");
+        assert_eq!(words[0].positives, 4);
+        assert!(words[0].p_value < 1e-5);
+        assert_eq!(
+            words[0]
+                .sites
+                .iter()
+                .map(|site| (site.sequence, site.start))
+                .collect::<Vec<_>>(),
+            vec![(0, 6), (1, 4), (2, 2), (3, 8)]
+        );
+        assert_eq!(words[0].pfm.sample_size, 4);
+    }
+
+    #[test]
+    fn test_enriched_gapped_words() {
+        let seqs = ["CACGAATGTG", "TACGCCTGTA", "GGACGTATGT", "ACGGCTGTCC"]
+            .into_iter()
+            .map(Sequence::from)
+            .collect::<Vec<_>>();
+        let config = SearchConfig {
+            hits: 1,
+            min_length: 6,
+            ..Default::default()
+        };
+        let words = WordConfig {
+            max_gap: 2,
+            mismatches: 0,
+            control: WordControl::Shuffled { seed: 1 },
+        };
+
+        let words = enriched_words(&seqs, &config, &words).unwrap();
+
+        assert_eq!(words[0].word, "ACGNNTGT");
+        assert_eq!(words[0].positives, 4);
+    }
+}